@@ -1,8 +1,9 @@
 use std::collections::HashSet;
 
-use bevy_math::{IVec3, Vec3};
+use bevy_math::{IVec3, UVec3, Vec3};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
-use svo_rs::{ManhattanDistance, SparseVoxelOctree, VoxelizedMesh};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use svo_rs::{ManhattanDistance, PathOptions, SparseVoxelOctree, VoxelizedMesh};
 
 fn compute_path_hashset(points: &HashSet<IVec3>, voxel_size: f32, area_haf_size: f32) {
     let start = (Vec3::new(-area_haf_size, -area_haf_size, -area_haf_size) / voxel_size).as_ivec3();
@@ -62,6 +63,29 @@ fn compute_path_octree(tree: &SparseVoxelOctree, area_haf_size: f32) {
     }
 }
 
+fn find_path_octree(tree: &SparseVoxelOctree, area_haf_size: f32, use_jps: bool) {
+    let start = tree
+        .find_node(Vec3::new(-area_haf_size, -area_haf_size, -area_haf_size))
+        .unwrap();
+    let end = tree
+        .find_node(Vec3::new(area_haf_size, area_haf_size, area_haf_size))
+        .unwrap();
+
+    let options = PathOptions::default();
+
+    let solution = if use_jps {
+        tree.find_path_jps(start, end, &options)
+    } else {
+        tree.find_path(start, end, &options)
+    };
+
+    if solution.is_none() {
+        println!("No path found");
+        println!("Start: {start:?}");
+        println!("Destination: {end:?}");
+    }
+}
+
 fn create_tree(voxel_size: f32, area_haf_size: f32) -> SparseVoxelOctree {
     let mut builder = svo_rs::SparseVoxelOctreeBuilder::new(voxel_size);
 
@@ -74,6 +98,32 @@ fn create_tree(voxel_size: f32, area_haf_size: f32) -> SparseVoxelOctree {
     builder.build()
 }
 
+/// Octree of 32x32x32 voxels with a fraction of them occupied at random, except for the corners
+/// the paths start and end in.
+fn create_cluttered_tree(fill: f64) -> SparseVoxelOctree {
+    let mut rng = StdRng::seed_from_u64(7);
+    let mut voxels = Vec::new();
+
+    for x in 0..32 {
+        for y in 0..32 {
+            for z in 0..32 {
+                let voxel = UVec3::new(x, y, z);
+
+                if voxel != UVec3::ZERO && voxel != UVec3::splat(31) && rng.gen_bool(fill) {
+                    voxels.push(voxel);
+                }
+            }
+        }
+    }
+
+    let mut builder = svo_rs::SparseVoxelOctreeBuilder::new(1.0);
+
+    builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::splat(-16)));
+    builder.set_bounds(Vec3::splat(-16.0), Vec3::splat(16.0));
+
+    builder.build()
+}
+
 fn create_hashset(voxel_size: f32) -> HashSet<IVec3> {
     let mut set = HashSet::new();
     for voxel in VoxelizedMesh::sphere(1.0, voxel_size, IVec3::ZERO).voxels() {
@@ -100,6 +150,20 @@ fn from_elem(c: &mut Criterion) {
                     b.iter(|| compute_path_octree(black_box(t), *area_haf_size));
                 },
             );
+            group.bench_with_input(
+                BenchmarkId::new("octree a*", format!("{voxel_size} {area_haf_size}")),
+                &tree,
+                |b, t| {
+                    b.iter(|| find_path_octree(black_box(t), *area_haf_size, false));
+                },
+            );
+            group.bench_with_input(
+                BenchmarkId::new("octree jps", format!("{voxel_size} {area_haf_size}")),
+                &tree,
+                |b, t| {
+                    b.iter(|| find_path_octree(black_box(t), *area_haf_size, true));
+                },
+            );
             group.bench_with_input(
                 BenchmarkId::new("hashset", format!("{voxel_size} {area_haf_size}")),
                 &hash_set,
@@ -112,5 +176,27 @@ fn from_elem(c: &mut Criterion) {
     }
 }
 
-criterion_group!(benches, from_elem);
+/// Jump point search is slower than A* on these maps, see `SparseVoxelOctree::find_path_jps`.
+///
+/// | fill | octree a* | octree jps |
+/// |------|-----------|------------|
+/// | 0.01 | 4.9 ms    | 6.3 ms     |
+/// | 0.1  | 15.0 ms   | 25.4 ms    |
+/// | 0.3  | 10.0 ms   | 15.6 ms    |
+fn cluttered(c: &mut Criterion) {
+    for fill in [0.01, 0.1, 0.3] {
+        let tree = create_cluttered_tree(fill);
+
+        let mut group = c.benchmark_group("Cluttered path");
+        group.bench_with_input(BenchmarkId::new("octree a*", fill), &tree, |b, t| {
+            b.iter(|| find_path_octree(black_box(t), 15.5, false));
+        });
+        group.bench_with_input(BenchmarkId::new("octree jps", fill), &tree, |b, t| {
+            b.iter(|| find_path_octree(black_box(t), 15.5, true));
+        });
+        group.finish();
+    }
+}
+
+criterion_group!(benches, from_elem, cluttered);
 criterion_main!(benches);
@@ -1,6 +1,9 @@
 use bevy::prelude::*;
 use bevy_render::prelude::shape::UVSphere;
-use svo_rs::{SparseVoxelOctree, SparseVoxelOctreeBuilder, SparseVoxelOctreeLink, VoxelizedMesh};
+use svo_rs::{
+    PathOptions, SparseVoxelOctree, SparseVoxelOctreeBuilder, SparseVoxelOctreeLink, Trajectory,
+    TrajectoryLimits, VoxelizedMesh,
+};
 
 fn main() {
     App::new()
@@ -33,9 +36,9 @@ struct Agent;
 #[derive(Component)]
 struct CalculatedPath {
     path: Vec<Vec3>,
-    current: usize,
-    progress: f32,
-    visited_nodes: Vec<SparseVoxelOctreeLink>,
+    trajectory: Trajectory,
+    time: f32,
+    path_nodes: Vec<SparseVoxelOctreeLink>,
 }
 
 fn setup(
@@ -154,45 +157,41 @@ fn calculate_path(
         let destination = (-transform.translation).normalize() * AREA_HALF_SIZE * 0.9;
 
         let start_point: Vec3 = transform.translation;
-        let end_point: Vec3 = destination;
+        let mut end_point: Vec3 = destination;
 
         let start = svo.tree.find_node(start_point).unwrap();
-        let end = svo.tree.find_node(end_point).unwrap();
+
+        // Destinations inside of geometry are moved to the closest free node.
+        let Some(end) = svo.tree.nearest_free_node(end_point) else {
+            println!("No free node near the destination {end_point:?}");
+            continue;
+        };
 
         if start == end {
             continue;
         }
 
-        let mut visited_nodes = Vec::new();
-
-        let solution = pathfinding::prelude::astar(
-            &start,
-            |n| {
-                visited_nodes.push(*n);
-                svo.tree
-                    .successors(*n)
-                    .into_iter()
-                    .map(|s| (s, 1))
-                    .collect::<Vec<_>>()
-            },
-            |n| n.distance_squared(&end, &svo.tree),
-            |n| *n == end,
-        );
-
-        if solution.is_none() {
+        let options = PathOptions {
+            allow_partial: true,
+            ..Default::default()
+        };
+
+        let Some(solution) = svo.tree.find_path(start, end, &options) else {
             println!("No path found");
             println!("Start: {start:?}");
             println!("Destination: {end:?}");
             continue;
-        }
+        };
 
-        let solution = solution.unwrap();
+        if solution.is_partial || svo.tree.find_node(end_point) != Some(end) {
+            end_point = svo.tree.node_position(*solution.links.last().unwrap());
+        }
 
         let mut path = vec![start_point];
 
-        for i in 0..solution.0.len() - 1 {
-            let start = solution.0[i];
-            let end = solution.0[i + 1];
+        for i in 0..solution.links.len() - 1 {
+            let start = solution.links[i];
+            let end = solution.links[i + 1];
 
             let face = svo
                 .tree
@@ -207,11 +206,24 @@ fn calculate_path(
         let path = subdivide_path(path, 4);
         let path = string_pulling_path(path, &svo.tree);
 
+        let limits = TrajectoryLimits {
+            max_velocity: AREA_HALF_SIZE,
+            max_acceleration: AREA_HALF_SIZE * 2.0,
+            max_jerk: AREA_HALF_SIZE * 8.0,
+            corner_deviation: VOXEL_SIZE / 4.0,
+            speed_per_clearance: AREA_HALF_SIZE * 2.0,
+            max_clearance: VOXEL_SIZE * 4.0,
+        };
+
+        let Some(trajectory) = svo.tree.trajectory(&path, &limits) else {
+            continue;
+        };
+
         commands.entity(entity).insert(CalculatedPath {
-            path: path.into_iter().map(std::convert::Into::into).collect(),
-            current: 0,
-            progress: 0.0,
-            visited_nodes,
+            path,
+            trajectory,
+            time: 0.0,
+            path_nodes: solution.links,
         });
     }
 }
@@ -224,37 +236,20 @@ fn follow_path(
     time: Res<Time>,
 ) {
     for (entity, mut transform, mut path) in &mut agents {
-        if !path.path.is_empty() {
-            if path.current >= path.path.len() - 1 {
-                commands.entity(entity).remove::<CalculatedPath>();
-                continue;
-            }
-
-            let current = path.path[path.current];
-            let next = path.path[path.current + 1];
-            let distance = (next - current).length();
-
-            transform.translation = current.lerp(next, path.progress / distance);
-
-            let speed = AREA_HALF_SIZE;
-            if path.progress > distance {
-                path.progress = 0.0;
-                path.current += 1;
-                if path.current >= path.path.len() - 1 {
-                    commands.entity(entity).remove::<CalculatedPath>();
-                }
-            }
-
-            path.progress += time.delta_seconds() * speed;
-
-            for i in 0..path.path.len() - 1 {
-                gizmos.line(path.path[i], path.path[i + 1], Color::rgb(1.0, 1.0, 1.0));
-            }
-
-            for node in &path.visited_nodes {
-                svo.tree.draw_node_gizmo(&mut gizmos, *node, Color::GREEN);
-            }
-            // gizmos.line(path.start, path.end, Color::rgb(1.0, 0.0, 0.0));
+        path.time += time.delta_seconds();
+        transform.translation = path.trajectory.position(path.time);
+
+        if path.time >= path.trajectory.duration() {
+            commands.entity(entity).remove::<CalculatedPath>();
+            continue;
+        }
+
+        for i in 0..path.path.len() - 1 {
+            gizmos.line(path.path[i], path.path[i + 1], Color::rgb(1.0, 1.0, 1.0));
+        }
+
+        for node in &path.path_nodes {
+            svo.tree.draw_node_gizmo(&mut gizmos, *node, Color::GREEN);
         }
     }
 }
@@ -0,0 +1,320 @@
+// Resource: https://papers.nips.cc/paper/2382-ara-anytime-a-with-provable-bounds-on-sub-optimality
+
+use std::{
+    collections::{BinaryHeap, HashMap, HashSet},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    path_finding::{reconstruct_path, OpenNode},
+    Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink,
+};
+
+/// Decrease of the weight of the heuristic after every improvement.
+const WEIGHT_STEP: f32 = 0.5;
+
+/// Anytime Repairing A* search that quickly finds a suboptimal path and improves it while
+/// time is available.
+///
+/// The heuristic is inflated by a weight, so the first path is found after expanding only a
+/// few links, but it may be up to that many times more expensive than the cheapest one. Every
+/// improvement lowers the weight by 0.5 and reuses the previous work, until the weight reaches
+/// 1 and the path is the cheapest one.
+///
+/// The search doesn't borrow the octree, the octree passed to [`AnytimeSearch::improve`] must
+/// not change while the search is in progress.
+///
+/// # Example
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use svo_rs::{AnytimeSearch, PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+/// use bevy_math::{IVec3, Vec3};
+///
+/// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+///
+/// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+/// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+///
+/// let octree = builder.build();
+///
+/// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+/// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+///
+/// let mut search = AnytimeSearch::new(&octree, start, goal, PathOptions::default(), 3.0);
+///
+/// while !search.is_finished() {
+///     // The path can be followed right away and is replaced as it gets cheaper.
+///     search.improve(&octree, Duration::from_millis(1));
+///
+///     assert!(search.suboptimality() <= 3.0 || search.path().is_none());
+/// }
+///
+/// assert_eq!(search.path().unwrap().links.last(), Some(&goal));
+/// assert_eq!(search.suboptimality(), 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnytimeSearch {
+    goal: SparseVoxelOctreeLink,
+    options: PathOptions,
+    weight: f32,
+    open: BinaryHeap<OpenNode>,
+    costs: HashMap<SparseVoxelOctreeLink, f32>,
+    parents: HashMap<SparseVoxelOctreeLink, SparseVoxelOctreeLink>,
+    /// Links expanded with the current weight.
+    closed: HashSet<SparseVoxelOctreeLink>,
+    /// Closed links whose cost decreased, expanded again with the next weight.
+    inconsistent: HashSet<SparseVoxelOctreeLink>,
+    path: Option<Path>,
+    suboptimality: f32,
+    is_finished: bool,
+}
+
+impl AnytimeSearch {
+    /// Starts a search between two links with the heuristic inflated by `initial_weight`,
+    /// which is never lower than 1. No links are expanded until [`AnytimeSearch::improve`] is
+    /// called.
+    #[must_use]
+    pub fn new(
+        octree: &SparseVoxelOctree,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: PathOptions,
+        initial_weight: f32,
+    ) -> Self {
+        let weight = initial_weight.max(1.0);
+
+        let mut search = Self {
+            goal,
+            options,
+            weight,
+            open: BinaryHeap::new(),
+            costs: HashMap::new(),
+            parents: HashMap::new(),
+            closed: HashSet::new(),
+            inconsistent: HashSet::new(),
+            path: None,
+            suboptimality: f32::INFINITY,
+            is_finished: false,
+        };
+
+        search.costs.insert(start, 0.0);
+        search.open.push(OpenNode {
+            estimate: octree.heuristic(start, goal) * weight,
+            cost: 0.0,
+            link: start,
+        });
+
+        search
+    }
+
+    /// Searches until the budget runs out or the cheapest path is found and returns the best
+    /// path found so far.
+    ///
+    /// Work left when the budget runs out continues with the next call.
+    pub fn improve(&mut self, octree: &SparseVoxelOctree, budget: Duration) -> Option<&Path> {
+        let deadline = Instant::now() + budget;
+
+        while !self.is_finished && self.search(octree, Some(deadline)) {
+            self.finish_iteration(octree);
+        }
+
+        self.path.as_ref()
+    }
+
+    /// Best path found so far.
+    #[must_use]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_ref()
+    }
+
+    /// Bound of the ratio between the cost of [`AnytimeSearch::path`] and the cost of the
+    /// cheapest path. Infinite until the first path is found.
+    #[must_use]
+    pub fn suboptimality(&self) -> f32 {
+        self.suboptimality
+    }
+
+    /// Current weight of the heuristic.
+    #[must_use]
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Returns true if the path is the cheapest one or there is no path.
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.is_finished
+    }
+
+    /// Expands links until the goal can't be improved with the current weight. Returns false
+    /// if the deadline passed first.
+    fn search(&mut self, octree: &SparseVoxelOctree, deadline: Option<Instant>) -> bool {
+        while let Some(node) = self.open.peek().copied() {
+            let goal_cost = self.costs.get(&self.goal).copied();
+
+            if goal_cost.is_some_and(|cost| cost <= node.estimate) {
+                return true;
+            }
+
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return false;
+            }
+
+            self.open.pop();
+
+            let OpenNode { cost, link, .. } = node;
+
+            if self.costs.get(&link).is_some_and(|best| cost > *best) || !self.closed.insert(link) {
+                continue;
+            }
+
+            for successor in octree.successors(link) {
+                let Some(edge_cost) = octree.edge_cost(link, successor, &self.options) else {
+                    continue;
+                };
+
+                let successor_cost = cost + edge_cost;
+
+                if self
+                    .costs
+                    .get(&successor)
+                    .is_some_and(|best| successor_cost >= *best)
+                {
+                    continue;
+                }
+
+                self.costs.insert(successor, successor_cost);
+                self.parents.insert(successor, link);
+
+                if self.closed.contains(&successor) {
+                    self.inconsistent.insert(successor);
+                } else {
+                    self.open.push(OpenNode {
+                        estimate: successor_cost
+                            + octree.heuristic(successor, self.goal) * self.weight,
+                        cost: successor_cost,
+                        link: successor,
+                    });
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Publishes the path of the finished iteration, updates the bound and lowers the weight.
+    fn finish_iteration(&mut self, octree: &SparseVoxelOctree) {
+        let Some(goal_cost) = self.costs.get(&self.goal).copied() else {
+            self.is_finished = true;
+            return;
+        };
+
+        self.path = Some(octree.path(reconstruct_path(&self.parents, self.goal), goal_cost));
+
+        // Open and inconsistent links without the inflation bound the cost of the cheapest path.
+        let mut pending = self
+            .open
+            .drain()
+            .filter(|node| self.costs.get(&node.link) == Some(&node.cost))
+            .map(|node| node.link)
+            .filter(|link| !self.closed.contains(link))
+            .collect::<HashSet<_>>();
+
+        pending.extend(self.inconsistent.drain());
+
+        let lower_bound = pending
+            .iter()
+            .map(|link| self.costs[link] + octree.heuristic(*link, self.goal))
+            .fold(f32::INFINITY, f32::min);
+
+        self.suboptimality = if lower_bound >= goal_cost {
+            1.0
+        } else {
+            self.weight.min(goal_cost / lower_bound)
+        };
+
+        if self.weight <= 1.0 || self.suboptimality <= 1.0 {
+            self.suboptimality = 1.0;
+            self.is_finished = true;
+            return;
+        }
+
+        self.weight = (self.weight - WEIGHT_STEP).max(1.0);
+        self.closed.clear();
+
+        for link in pending {
+            let cost = self.costs[&link];
+
+            self.open.push(OpenNode {
+                estimate: cost + octree.heuristic(link, self.goal) * self.weight,
+                cost,
+                link,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::sphere;
+
+    use super::*;
+
+    #[test]
+    fn test_anytime_search_improves_to_optimal() {
+        let tree = sphere(4.0);
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+
+        let optimal = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        let mut search = AnytimeSearch::new(&tree, start, goal, PathOptions::default(), 3.0);
+
+        assert!(search.path().is_none());
+        assert!(search.improve(&tree, Duration::ZERO).is_none());
+
+        // The first iteration is bounded by the initial weight.
+        assert!(search.search(&tree, None));
+        search.finish_iteration(&tree);
+
+        let first = search.path().unwrap().clone();
+
+        assert_eq!(first.links.last(), Some(&goal));
+        assert!(search.suboptimality() <= 3.0);
+        assert!(first.cost <= optimal.cost * search.suboptimality() + 1e-3);
+
+        let mut previous = search.suboptimality();
+
+        while !search.is_finished() {
+            let path = search.improve(&tree, Duration::from_secs(10)).unwrap();
+
+            assert!(path.cost <= first.cost + 1e-3);
+            assert!(search.suboptimality() <= previous);
+
+            previous = search.suboptimality();
+        }
+
+        assert!((search.path().unwrap().cost - optimal.cost).abs() < 1e-3);
+        assert!((search.suboptimality() - 1.0).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_anytime_search_to_unreachable_goal() {
+        let tree = sphere(4.0);
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let inside = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        let mut search = AnytimeSearch::new(&tree, start, inside, PathOptions::default(), 2.0);
+
+        assert!(search.improve(&tree, Duration::from_secs(10)).is_none());
+        assert!(search.is_finished());
+    }
+}
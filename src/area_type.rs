@@ -0,0 +1,50 @@
+/// Area type of a voxel or of a whole node in the sparse voxel octree.
+///
+/// Area types tag free space (water, restricted airspace, ...) as well as filled voxels
+/// (glass, concrete, ...). There are at most 64 area types, so a set of them fits into a single
+/// `u64`. The area type with id 0 is the default one.
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::AreaType;
+///
+/// const WATER: AreaType = AreaType::new(1);
+///
+/// assert_eq!(WATER.id(), 1);
+/// assert_eq!(AreaType::default(), AreaType::DEFAULT);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+pub struct AreaType(u8);
+
+impl AreaType {
+    /// Area type of everything that wasn't tagged.
+    pub const DEFAULT: AreaType = AreaType(0);
+
+    /// Maximum number of area types.
+    pub const COUNT: u8 = 64;
+
+    /// Creates an area type from its id.
+    ///
+    /// # Panics
+    /// Panics if the id is not lower than [`AreaType::COUNT`].
+    #[must_use]
+    pub const fn new(id: u8) -> Self {
+        assert!(id < Self::COUNT, "area type id must be lower than 64");
+
+        Self(id)
+    }
+
+    /// Returns the id of the area type.
+    #[must_use]
+    pub const fn id(self) -> u8 {
+        self.0
+    }
+
+    /// Returns the bit of the area type in a `u64` mask of area types.
+    #[must_use]
+    #[inline]
+    pub const fn flag(self) -> u64 {
+        1 << self.0
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{
+    path_finding::SearchScratch, Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink,
+};
+
+/// Single query of [`SparseVoxelOctree::find_paths`].
+#[derive(Debug, Clone)]
+pub struct PathRequest {
+    /// Link the path starts in.
+    pub start: SparseVoxelOctreeLink,
+    /// Link the path leads to.
+    pub goal: SparseVoxelOctreeLink,
+    /// Options of the search.
+    pub options: PathOptions,
+}
+
+/// Result of a [`PathRequest`], the same as the result of [`SparseVoxelOctree::find_path`].
+pub type PathResult = Option<Path>;
+
+impl SparseVoxelOctree {
+    /// Finds paths for many requests at once.
+    ///
+    /// With the `rayon` feature enabled the requests are searched in parallel on the global
+    /// thread pool, otherwise one after another. The open and closed sets of the searches are
+    /// reused between the requests handled by the same thread, which saves most of the
+    /// allocations of calling [`SparseVoxelOctree::find_path`] in a loop. Results are in the
+    /// order of the requests.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, PathRequest, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    /// let requests = (0..8)
+    ///     .map(|i| PathRequest {
+    ///         start: octree.find_node(Vec3::new(-6.5, i as f32 - 3.5, 0.5)).unwrap(),
+    ///         goal,
+    ///         options: PathOptions::default(),
+    ///     })
+    ///     .collect::<Vec<_>>();
+    ///
+    /// let results = octree.find_paths(&requests);
+    ///
+    /// assert!(results.iter().all(|path| path.as_ref().unwrap().links.last() == Some(&goal)));
+    /// ```
+    #[must_use]
+    pub fn find_paths(&self, requests: &[PathRequest]) -> Vec<PathResult> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+
+            requests
+                .par_iter()
+                .map_init(SearchScratch::default, |scratch, request| {
+                    self.find_requested_path(scratch, request)
+                })
+                .collect()
+        }
+
+        #[cfg(not(feature = "rayon"))]
+        {
+            let mut scratch = SearchScratch::default();
+
+            requests
+                .iter()
+                .map(|request| self.find_requested_path(&mut scratch, request))
+                .collect()
+        }
+    }
+
+    fn find_requested_path(
+        &self,
+        scratch: &mut SearchScratch,
+        request: &PathRequest,
+    ) -> PathResult {
+        let PathRequest {
+            start,
+            goal,
+            options,
+        } = request;
+
+        self.search_with(
+            scratch,
+            *start,
+            options,
+            |_| true,
+            |link| link == *goal,
+            |link| self.heuristic(link, *goal),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::sphere;
+
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn requests(tree: &SparseVoxelOctree) -> Vec<PathRequest> {
+        let goal = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+        let inside = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        let mut requests = Vec::new();
+
+        for x in [-6.5, 6.5] {
+            for y in [-6.5, -2.5, 2.5, 6.5] {
+                for z in [-6.5, 6.5] {
+                    requests.push(PathRequest {
+                        start: tree.find_node(Vec3::new(x, y, z)).unwrap(),
+                        goal,
+                        options: PathOptions::default(),
+                    });
+                }
+            }
+        }
+
+        requests.push(PathRequest {
+            start: goal,
+            goal: inside,
+            options: PathOptions::default(),
+        });
+
+        requests
+    }
+
+    #[test]
+    fn test_batch_matches_single_queries() {
+        let tree = sphere(3.0);
+        let requests = requests(&tree);
+
+        let results = tree.find_paths(&requests);
+
+        assert_eq!(results.len(), requests.len());
+        assert!(results.last().unwrap().is_none());
+
+        for (request, result) in requests.iter().zip(&results) {
+            assert_eq!(
+                result,
+                &tree.find_path(request.start, request.goal, &request.options)
+            );
+        }
+    }
+
+    #[test]
+    fn test_batch_from_another_thread() {
+        assert_send_sync::<SparseVoxelOctree>();
+
+        let tree = sphere(3.0);
+        let requests = requests(&tree);
+
+        // The octree can be shared between threads without any locking.
+        let from_thread = std::thread::scope(|scope| {
+            scope
+                .spawn(|| tree.find_paths(&requests[..2]))
+                .join()
+                .unwrap()
+        });
+
+        assert_eq!(from_thread, tree.find_paths(&requests[..2]));
+    }
+}
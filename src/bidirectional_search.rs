@@ -0,0 +1,215 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{
+    path_finding::{reconstruct_path, OpenNode},
+    Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink,
+};
+
+/// Open and closed sets of one direction of a bidirectional search.
+#[derive(Debug, Default)]
+struct Frontier {
+    open: BinaryHeap<OpenNode>,
+    costs: HashMap<SparseVoxelOctreeLink, f32>,
+    /// Next link towards the end the frontier started from.
+    parents: HashMap<SparseVoxelOctreeLink, SparseVoxelOctreeLink>,
+}
+
+impl Frontier {
+    fn new(link: SparseVoxelOctreeLink, estimate: f32) -> Self {
+        let mut frontier = Self::default();
+
+        frontier.costs.insert(link, 0.0);
+        frontier.open.push(OpenNode {
+            estimate,
+            cost: 0.0,
+            link,
+        });
+
+        frontier
+    }
+
+    /// Lowest estimate of the open set, a lower bound of the cost of any path not found yet.
+    fn min_estimate(&self) -> f32 {
+        self.open.peek().map_or(f32::INFINITY, |node| node.estimate)
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Finds the cheapest path between two links using bidirectional A*.
+    ///
+    /// One search runs from the start and another one backwards from the goal, always
+    /// expanding the smaller of the two open sets, until the cheapest path where they meet
+    /// can't be improved anymore. The costs are the same as in [`SparseVoxelOctree::find_path`],
+    /// but long paths through open space usually expand far fewer links.
+    /// [`PathOptions::allow_partial`] is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let path = octree
+    ///     .find_path_bidirectional(start, goal, &PathOptions::default())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(path.links.first(), Some(&start));
+    /// assert_eq!(path.links.last(), Some(&goal));
+    /// ```
+    #[must_use]
+    pub fn find_path_bidirectional(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: &PathOptions,
+    ) -> Option<Path> {
+        let heuristic = self.heuristic(start, goal);
+
+        let mut forward = Frontier::new(start, heuristic);
+        let mut backward = Frontier::new(goal, heuristic);
+
+        // Cheapest path found so far and the link where the two searches meet on it.
+        let mut best = (start == goal).then_some((0.0, start));
+
+        while !forward.open.is_empty() && !backward.open.is_empty() {
+            let bound = forward.min_estimate().max(backward.min_estimate());
+
+            if best.is_some_and(|(cost, _)| cost <= bound) {
+                break;
+            }
+
+            let is_forward = forward.open.len() <= backward.open.len();
+
+            let (frontier, other, target) = if is_forward {
+                (&mut forward, &backward, goal)
+            } else {
+                (&mut backward, &forward, start)
+            };
+
+            let Some(OpenNode { cost, link, .. }) = frontier.open.pop() else {
+                break;
+            };
+
+            if frontier.costs.get(&link).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            let neighbors = if is_forward {
+                self.successors(link)
+            } else {
+                self.predecessors(link)
+            };
+
+            for neighbor in neighbors {
+                let edge_cost = if is_forward {
+                    self.edge_cost(link, neighbor, options)
+                } else {
+                    self.edge_cost(neighbor, link, options)
+                };
+
+                let Some(edge_cost) = edge_cost else {
+                    continue;
+                };
+
+                let neighbor_cost = cost + edge_cost;
+
+                if frontier
+                    .costs
+                    .get(&neighbor)
+                    .is_some_and(|best| neighbor_cost >= *best)
+                {
+                    continue;
+                }
+
+                frontier.costs.insert(neighbor, neighbor_cost);
+                frontier.parents.insert(neighbor, link);
+                frontier.open.push(OpenNode {
+                    estimate: neighbor_cost + self.heuristic(neighbor, target),
+                    cost: neighbor_cost,
+                    link: neighbor,
+                });
+
+                if let Some(remaining) = other.costs.get(&neighbor) {
+                    let total = neighbor_cost + remaining;
+
+                    if best.map_or(true, |(cost, _)| total < cost) {
+                        best = Some((total, neighbor));
+                    }
+                }
+            }
+        }
+
+        let (cost, meeting) = best?;
+
+        let mut links = reconstruct_path(&forward.parents, meeting);
+        let mut current = meeting;
+
+        while let Some(next) = backward.parents.get(&current) {
+            links.push(*next);
+            current = *next;
+        }
+
+        Some(self.path(links, cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::sphere;
+
+    use super::*;
+
+    #[test]
+    fn test_bidirectional_search_matches_find_path() {
+        let tree = sphere(3.0);
+        let options = PathOptions::default();
+
+        let pairs = [
+            (Vec3::new(-6.5, 0.5, 0.5), Vec3::new(6.5, 0.5, 0.5)),
+            (Vec3::new(-6.5, -6.5, -6.5), Vec3::new(6.5, 6.5, 6.5)),
+            (Vec3::new(0.5, 6.5, 0.5), Vec3::new(0.5, -6.5, 0.5)),
+            (Vec3::new(3.5, 3.5, 3.5), Vec3::new(3.5, 3.5, 3.5)),
+        ];
+
+        for (start, goal) in pairs {
+            let start = tree.find_node(start).unwrap();
+            let goal = tree.find_node(goal).unwrap();
+
+            let path = tree.find_path_bidirectional(start, goal, &options).unwrap();
+            let expected = tree.find_path(start, goal, &options).unwrap();
+
+            assert_eq!(path.links.first(), Some(&start));
+            assert_eq!(path.links.last(), Some(&goal));
+            assert!((path.cost - expected.cost).abs() < 1e-3);
+
+            for pair in path.links.windows(2) {
+                assert!(tree.successors(pair[0]).contains(&pair[1]));
+            }
+        }
+    }
+
+    #[test]
+    fn test_bidirectional_search_to_unreachable_goal() {
+        let tree = sphere(3.0);
+
+        // Links inside of the sphere can't be reached from either side.
+        let inside = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+        let outside = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+
+        assert!(tree
+            .find_path_bidirectional(outside, inside, &PathOptions::default())
+            .is_none());
+    }
+}
@@ -0,0 +1,541 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy_math::Vec3;
+
+use crate::{
+    cohen_sutherland::{cohen_sutherland, LineClippingResult},
+    path_cache::Change,
+    Path, SparseVoxelOctree, SparseVoxelOctreeLink, VolumeShape,
+};
+
+/// Identifier of a blocker returned by [`SparseVoxelOctree::add_blocker`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlockerId(pub(crate) u32);
+
+/// Identifier of a path returned by [`SparseVoxelOctree::register_path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathId(u32);
+
+/// Result of enabling or disabling a blocker.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockerChange {
+    /// Links whose passability changed.
+    pub links: Vec<SparseVoxelOctreeLink>,
+    /// Registered paths that cross the blocker and are not passable anymore.
+    pub invalidated_paths: Vec<PathId>,
+}
+
+#[derive(Debug, Clone)]
+struct Blocker {
+    shape: VolumeShape,
+    enabled: bool,
+    /// Nodes and subnodes covered by the blocker.
+    links: Vec<SparseVoxelOctreeLink>,
+}
+
+/// Blockers of an octree together with the links they cover.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Blockers {
+    entries: HashMap<BlockerId, Blocker>,
+    /// Number of enabled blockers covering a link.
+    blocked: HashMap<SparseVoxelOctreeLink, u32>,
+    /// Number of blockers covering voxels of a leaf node.
+    covered_leafs: HashMap<usize, u32>,
+    paths: HashMap<PathId, Vec<SparseVoxelOctreeLink>>,
+    next_blocker_id: u32,
+    next_path_id: u32,
+}
+
+impl Blockers {
+    #[inline]
+    pub(crate) fn is_blocked(&self, link: SparseVoxelOctreeLink) -> bool {
+        !self.blocked.is_empty() && self.blocked.contains_key(&link)
+    }
+
+    /// Returns true if a leaf node has voxels covered by a blocker. Such leaf is always
+    /// navigated through its subnodes.
+    #[inline]
+    pub(crate) fn covers_leaf(&self, node_index: usize) -> bool {
+        !self.covered_leafs.is_empty() && self.covered_leafs.contains_key(&node_index)
+    }
+
+    fn block(&mut self, links: &[SparseVoxelOctreeLink]) {
+        for link in links {
+            *self.blocked.entry(*link).or_default() += 1;
+        }
+    }
+
+    fn unblock(&mut self, links: &[SparseVoxelOctreeLink]) {
+        for link in links {
+            if let Some(count) = self.blocked.get_mut(link) {
+                *count -= 1;
+
+                if *count == 0 {
+                    self.blocked.remove(link);
+                }
+            }
+        }
+    }
+
+    /// Marks the leaf nodes of covered subnodes. Returns the leaf nodes that weren't covered by
+    /// any blocker before.
+    fn cover(&mut self, links: &[SparseVoxelOctreeLink]) -> Vec<SparseVoxelOctreeLink> {
+        let mut toggled = Vec::new();
+
+        for node_index in leaf_indexes(links) {
+            let count = self.covered_leafs.entry(node_index).or_default();
+            *count += 1;
+
+            if *count == 1 {
+                toggled.push(SparseVoxelOctreeLink::new(0, node_index, None));
+            }
+        }
+
+        toggled
+    }
+
+    /// Reverts [`Blockers::cover`]. Returns the leaf nodes that aren't covered by any blocker
+    /// anymore.
+    fn uncover(&mut self, links: &[SparseVoxelOctreeLink]) -> Vec<SparseVoxelOctreeLink> {
+        let mut toggled = Vec::new();
+
+        for node_index in leaf_indexes(links) {
+            if let Some(count) = self.covered_leafs.get_mut(&node_index) {
+                *count -= 1;
+
+                if *count == 0 {
+                    self.covered_leafs.remove(&node_index);
+                    toggled.push(SparseVoxelOctreeLink::new(0, node_index, None));
+                }
+            }
+        }
+
+        toggled
+    }
+}
+
+/// Indexes of the leaf nodes of subnodes, without duplicates.
+fn leaf_indexes(links: &[SparseVoxelOctreeLink]) -> Vec<usize> {
+    let mut indexes = links
+        .iter()
+        .filter(|link| link.subnode_index.is_some())
+        .map(|link| link.node_index)
+        .collect::<Vec<_>>();
+
+    indexes.sort_unstable();
+    indexes.dedup();
+
+    indexes
+}
+
+impl SparseVoxelOctree {
+    /// Registers a blocker, such as a door or a force field, covering a shape in world space.
+    ///
+    /// Nodes crossing the border of the shape are subdivided once when the blocker is added,
+    /// so enabling and disabling it later doesn't change the octree. Voxels with the center
+    /// inside of the shape are covered. While a blocker is enabled, the covered nodes are not
+    /// returned by [`SparseVoxelOctree::successors`] and block
+    /// [`SparseVoxelOctree::is_in_line_of_sight`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VolumeShape};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    ///
+    /// let door = octree.add_blocker(
+    ///     VolumeShape::Box { min: Vec3::new(0.0, 0.0, 0.0), max: Vec3::new(1.0, 2.0, 2.0) },
+    ///     false,
+    /// );
+    ///
+    /// let link = octree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+    ///
+    /// assert!(!octree.is_blocked(link));
+    ///
+    /// let change = octree.set_blocker_enabled(door, true).unwrap();
+    ///
+    /// assert!(change.links.contains(&link));
+    /// assert!(octree.is_blocked(link));
+    /// ```
+    pub fn add_blocker(&mut self, shape: VolumeShape, enabled: bool) -> BlockerId {
+        let id = BlockerId(self.blockers.next_blocker_id);
+        self.blockers.next_blocker_id += 1;
+
+        // Subdivided nodes and leaf nodes that are navigated through their subnodes now.
+        let mut changed = Vec::new();
+        let links = self.covered_links(&shape, Some(&mut changed));
+
+        changed.extend(self.blockers.cover(&links));
+
+        if enabled {
+            self.blockers.block(&links);
+        }
+
+        // Resolves the other blockers before the new one is added, its links are up to date.
+        self.resolve_links(&changed);
+
+        self.blockers.entries.insert(
+            id,
+            Blocker {
+                shape,
+                enabled,
+                links,
+            },
+        );
+
+        self.record_change(Change::Volume(shape));
+
+        id
+    }
+
+    /// Removes a blocker. Returns false if there was no blocker with the id.
+    pub fn remove_blocker(&mut self, id: BlockerId) -> bool {
+        let Some(blocker) = self.blockers.entries.remove(&id) else {
+            return false;
+        };
+
+        if blocker.enabled {
+            self.blockers.unblock(&blocker.links);
+            self.record_change(Change::Links(blocker.links.clone()));
+        }
+
+        let uncovered = self.blockers.uncover(&blocker.links);
+
+        if !uncovered.is_empty() {
+            self.resolve_off_mesh_links_at(&uncovered);
+            self.record_change(Change::Links(uncovered));
+        }
+
+        true
+    }
+
+    /// Enables or disables a blocker.
+    ///
+    /// Returns the links whose passability changed and the registered paths that are not
+    /// passable anymore, or `None` if there is no blocker with the id.
+    pub fn set_blocker_enabled(&mut self, id: BlockerId, enabled: bool) -> Option<BlockerChange> {
+        let blocker = self.blockers.entries.get_mut(&id)?;
+
+        if blocker.enabled == enabled {
+            return Some(BlockerChange::default());
+        }
+
+        blocker.enabled = enabled;
+        let links = blocker.links.clone();
+
+        self.record_change(Change::Links(links.clone()));
+
+        if !enabled {
+            self.blockers.unblock(&links);
+
+            return Some(BlockerChange {
+                links,
+                invalidated_paths: Vec::new(),
+            });
+        }
+
+        self.blockers.block(&links);
+
+        let covered = links.iter().collect::<HashSet<_>>();
+        let mut invalidated_paths = self
+            .blockers
+            .paths
+            .iter()
+            .filter(|(_, path)| path.iter().any(|link| covered.contains(link)))
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        invalidated_paths.sort();
+
+        Some(BlockerChange {
+            links,
+            invalidated_paths,
+        })
+    }
+
+    /// Returns true if a link is covered by an enabled blocker.
+    #[must_use]
+    pub fn is_blocked(&self, link: SparseVoxelOctreeLink) -> bool {
+        self.blockers.is_blocked(link)
+    }
+
+    /// Registers a path, so [`SparseVoxelOctree::set_blocker_enabled`] reports when it gets
+    /// blocked.
+    pub fn register_path(&mut self, path: &Path) -> PathId {
+        let id = PathId(self.blockers.next_path_id);
+        self.blockers.next_path_id += 1;
+
+        self.blockers.paths.insert(id, path.links.clone());
+
+        id
+    }
+
+    /// Removes a registered path. Returns false if there was no path with the id.
+    pub fn unregister_path(&mut self, id: PathId) -> bool {
+        self.blockers.paths.remove(&id).is_some()
+    }
+
+    /// Returns true if a segment given in voxel coordinates relative to the origin crosses a
+    /// node covered by an enabled blocker.
+    pub(crate) fn is_segment_blocked(&self, from: Vec3, to: Vec3) -> bool {
+        let origin = self.origin.as_vec3();
+        let (segment_min, segment_max) = (from.min(to), from.max(to));
+
+        self.blockers.blocked.keys().any(|link| {
+            let (min, max) = self.link_bounds(*link);
+            let (min, max) = (
+                min / self.voxel_size - origin,
+                max / self.voxel_size - origin,
+            );
+
+            // Nodes outside of the bounds of the segment can't be crossed by it.
+            if max.cmplt(segment_min).any() || min.cmpgt(segment_max).any() {
+                return false;
+            }
+
+            cohen_sutherland(
+                &from.to_array(),
+                &to.to_array(),
+                &min.to_array(),
+                &max.to_array(),
+            ) != LineClippingResult::Outside
+        })
+    }
+
+    /// Recomputes the links covered by the blockers overlapping nodes that were subdivided.
+    ///
+    /// Returns the leaf nodes whose voxels got covered or uncovered.
+    pub(crate) fn resolve_blockers_at(
+        &mut self,
+        subdivided: &[SparseVoxelOctreeLink],
+    ) -> Vec<SparseVoxelOctreeLink> {
+        if subdivided.is_empty() || self.blockers.entries.is_empty() {
+            return Vec::new();
+        }
+
+        let bounds = subdivided
+            .iter()
+            .map(|link| self.link_bounds(*link))
+            .collect::<Vec<_>>();
+
+        let mut blockers = std::mem::take(&mut self.blockers.entries);
+        let mut toggled = Vec::new();
+
+        for blocker in blockers.values_mut() {
+            if !bounds
+                .iter()
+                .any(|(min, max)| blocker.shape.intersects_box(*min, *max))
+            {
+                continue;
+            }
+
+            let links = self.covered_links(&blocker.shape, None);
+
+            if blocker.enabled {
+                self.blockers.unblock(&blocker.links);
+                self.blockers.block(&links);
+            }
+
+            toggled.extend(self.blockers.uncover(&blocker.links));
+            toggled.extend(self.blockers.cover(&links));
+
+            blocker.links = links;
+        }
+
+        self.blockers.entries = blockers;
+
+        toggled
+    }
+
+    /// Finds nodes and subnodes covered by a shape.
+    ///
+    /// If `subdivided` is given, air nodes crossing the border of the shape are subdivided and
+    /// added to it.
+    fn covered_links(
+        &mut self,
+        shape: &VolumeShape,
+        mut subdivided: Option<&mut Vec<SparseVoxelOctreeLink>>,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        let subdivide = subdivided.is_some();
+        let mut links = Vec::new();
+        let mut open = vec![SparseVoxelOctreeLink::new(self.layers.len() - 1, 0, None)];
+
+        while let Some(link) = open.pop() {
+            let (min, max) = self.link_bounds(link);
+
+            if !shape.intersects_box(min, max) {
+                continue;
+            }
+
+            let node = &self.layers[link.layer_index][link.node_index];
+
+            if node.is_leaf {
+                for subnode in 0..64 {
+                    let subnode_link =
+                        SparseVoxelOctreeLink::new(0, link.node_index, Some(subnode));
+
+                    if shape.contains_point(self.node_position(subnode_link)) {
+                        links.push(subnode_link);
+                    }
+                }
+
+                continue;
+            }
+
+            if node.first_child.is_none() {
+                if shape.contains_box(min, max)
+                    || (!subdivide && shape.contains_point(self.node_position(link)))
+                {
+                    links.push(link);
+                    continue;
+                }
+
+                let Some(subdivided) = subdivided.as_deref_mut() else {
+                    continue;
+                };
+
+                self.subdivide(link);
+                subdivided.push(link);
+            }
+
+            if let Some(first_child) = self.layers[link.layer_index][link.node_index].first_child {
+                for child in 0..8 {
+                    open.push(SparseVoxelOctreeLink::new(
+                        first_child.layer_index,
+                        first_child.node_index + child,
+                        None,
+                    ));
+                }
+            }
+        }
+
+        links
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_fixtures::wall_with_door, PathOptions, SparseVoxelOctreeBuilder};
+
+    use super::*;
+
+    const FROM: Vec3 = Vec3::new(-3.5, 1.0, 1.0);
+    const TO: Vec3 = Vec3::new(3.5, 1.0, 1.0);
+
+    fn ends(tree: &SparseVoxelOctree) -> (SparseVoxelOctreeLink, SparseVoxelOctreeLink) {
+        (
+            tree.find_node(Vec3::new(-3.5, 1.5, 1.5)).unwrap(),
+            tree.find_node(Vec3::new(3.5, 1.5, 1.5)).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_door_blocks_paths_and_line_of_sight() {
+        let (mut tree, door) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        let path = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+        let path_id = tree.register_path(&path);
+
+        assert!(tree.is_in_line_of_sight(FROM, TO));
+
+        let change = tree.set_blocker_enabled(door, true).unwrap();
+
+        assert_eq!(change.links.len(), 4);
+        assert_eq!(change.invalidated_paths, vec![path_id]);
+        assert!(!tree.is_in_line_of_sight(FROM, TO));
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_none());
+
+        let change = tree.set_blocker_enabled(door, false).unwrap();
+
+        assert_eq!(change.links.len(), 4);
+        assert!(change.invalidated_paths.is_empty());
+        assert!(tree.is_in_line_of_sight(FROM, TO));
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_some());
+
+        assert!(tree.unregister_path(path_id));
+        assert!(!tree.unregister_path(path_id));
+    }
+
+    #[test]
+    fn test_door_stays_closed_after_subdivision() {
+        let (mut tree, door) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        tree.set_blocker_enabled(door, true).unwrap();
+        tree.set_voxel(Vec3::new(-5.5, 5.5, 5.5), true);
+
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_remove_door() {
+        let (mut tree, door) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        tree.set_blocker_enabled(door, true).unwrap();
+
+        assert!(tree.remove_blocker(door));
+        assert!(!tree.remove_blocker(door));
+        assert!(tree.set_blocker_enabled(door, true).is_none());
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_some());
+    }
+
+    #[test]
+    fn test_blockers_follow_subdivision() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let mut tree = builder.build();
+
+        // Covers a whole octant and a part of a leaf node far away from it.
+        let octant = tree.add_blocker(
+            VolumeShape::Box {
+                min: Vec3::new(0.0, 0.0, 0.0),
+                max: Vec3::new(8.0, 8.0, 8.0),
+            },
+            true,
+        );
+        let corner = tree.add_blocker(
+            VolumeShape::Box {
+                min: Vec3::new(-8.0, -8.0, -8.0),
+                max: Vec3::new(-7.0, -7.0, -7.0),
+            },
+            true,
+        );
+
+        let corner_links = tree.blockers.entries[&corner].links.clone();
+        let leaf = tree.find_node(Vec3::new(-7.5, -7.5, -7.5)).unwrap();
+
+        assert_eq!(tree.blockers.entries[&octant].links.len(), 1);
+        assert!(tree.blockers.covers_leaf(leaf.node_index));
+
+        // Subdividing the octant replaces its link by the links of the new nodes.
+        tree.set_voxel(Vec3::new(4.5, 4.5, 4.5), true);
+
+        let link = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        assert!(tree.blockers.entries[&octant].links.len() > 1);
+        assert!(tree.is_blocked(link));
+        assert_eq!(tree.blockers.entries[&corner].links, corner_links);
+
+        assert!(tree.remove_blocker(corner));
+        assert!(!tree.blockers.covers_leaf(leaf.node_index));
+        assert!(!tree.is_blocked(tree.find_node(Vec3::new(-7.5, -7.5, -7.5)).unwrap()));
+    }
+}
@@ -47,6 +47,12 @@ impl CompoundNode {
         Self(0)
     }
 
+    /// Creates a node from raw bits where each bit represents a voxel in Morton order.
+    #[inline]
+    pub fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
     /// Returns true if the `node_index` is part of a cube's face defined by `face_index`.
     #[inline]
     pub fn is_face(node_index: u8, face_index: usize) -> bool {
@@ -96,6 +102,16 @@ impl CompoundNode {
         self.0 & (1 << index) != 0
     }
 
+    /// Sets voxel at a specific Morton code index `index` to `value` (true = filled, false = empty)
+    #[inline]
+    pub fn set_by_index(&mut self, index: u8, value: bool) {
+        if value {
+            self.0 |= 1 << index;
+        } else {
+            self.0 &= !(1 << index);
+        }
+    }
+
     /// Gets all indexes of filled voxels.
     #[inline]
     pub fn get_occupied_indexes(&self) -> Vec<u8> {
@@ -0,0 +1,348 @@
+// Resource: https://www.aaai.org/ocs/index.php/AAAI/AAAI12/paper/view/5062
+
+use std::collections::HashMap;
+
+use crate::{
+    distance_field::LazyDistanceField, CooperativeAgent, CooperativeOptions, Path,
+    ReservationTable, SparseVoxelOctree, SparseVoxelOctreeLink,
+};
+
+/// Options of [`SparseVoxelOctree::find_conflict_free_paths`].
+#[derive(Debug, Clone)]
+pub struct ConflictBasedOptions {
+    /// Options of the space-time search of single agents. Conflicts are resolved only within
+    /// its window.
+    pub cooperative: CooperativeOptions,
+    /// Maximal number of expanded nodes of the constraint tree before the search gives up.
+    pub max_expansions: usize,
+    /// Allowed ratio between the cost of the found paths and the cost of the optimal ones. Values
+    /// above 1 prefer solutions with fewer conflicts, which are usually found much faster.
+    pub suboptimality: f32,
+}
+
+impl Default for ConflictBasedOptions {
+    fn default() -> Self {
+        Self {
+            cooperative: CooperativeOptions::default(),
+            max_expansions: 1000,
+            suboptimality: 1.0,
+        }
+    }
+}
+
+/// Restriction of a single agent added to resolve a conflict.
+#[derive(Debug, Clone, Copy)]
+enum Constraint {
+    /// The agent can't be in the link at the time step.
+    Vertex {
+        agent: usize,
+        link: SparseVoxelOctreeLink,
+        time: u32,
+    },
+    /// The agent can't move between the links starting at the time step.
+    Edge {
+        agent: usize,
+        from: SparseVoxelOctreeLink,
+        to: SparseVoxelOctreeLink,
+        time: u32,
+    },
+}
+
+impl Constraint {
+    fn agent(&self) -> usize {
+        match *self {
+            Constraint::Vertex { agent, .. } | Constraint::Edge { agent, .. } => agent,
+        }
+    }
+}
+
+/// Node of the constraint tree.
+#[derive(Debug, Clone)]
+struct ConstraintNode {
+    constraints: Vec<Constraint>,
+    paths: Vec<Path>,
+    cost: f32,
+    conflicts: usize,
+}
+
+impl SparseVoxelOctree {
+    /// Finds paths of multiple agents that never collide using Conflict-Based Search.
+    ///
+    /// Paths are planned separately by the space-time search of
+    /// [`SparseVoxelOctree::find_space_time_path`]. Whenever more agents than fit into a link
+    /// meet there or two agents swap places through links holding a single agent, the search
+    /// branches on constraints forbidding it. With `suboptimality` of 1 the sum of the costs
+    /// of the paths is minimal, higher values turn the search into Enhanced CBS.
+    ///
+    /// Returns the paths in the order of `agents`, where the link at index `i` is occupied at
+    /// time step `i`, or `None` if no solution was found within `max_expansions`. Priorities of
+    /// the agents are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{CooperativeAgent, ConflictBasedOptions, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let a = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let b = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let agents = [
+    ///     CooperativeAgent { start: a, goal: b, priority: 0 },
+    ///     CooperativeAgent { start: b, goal: a, priority: 0 },
+    /// ];
+    ///
+    /// let paths = octree
+    ///     .find_conflict_free_paths(&agents, &ConflictBasedOptions::default())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(paths[0].links.last(), Some(&b));
+    /// assert_eq!(paths[1].links.last(), Some(&a));
+    /// ```
+    #[must_use]
+    pub fn find_conflict_free_paths(
+        &self,
+        agents: &[CooperativeAgent],
+        options: &ConflictBasedOptions,
+    ) -> Option<Vec<Path>> {
+        // Distances to the goals are shared by agents with the same goal and all replans.
+        let mut distances = HashMap::new();
+
+        let mut plan = |agent: usize, constraints: &[Constraint]| {
+            let mut table = ReservationTable::new();
+
+            for constraint in constraints.iter().filter(|c| c.agent() == agent) {
+                match *constraint {
+                    Constraint::Vertex { link, time, .. } => table.forbid(link, time),
+                    Constraint::Edge { from, to, time, .. } => table.forbid_move(from, to, time),
+                }
+            }
+
+            let goal = agents[agent].goal;
+            let distances = distances
+                .entry(goal)
+                .or_insert_with(|| LazyDistanceField::new(&[goal]));
+
+            self.find_space_time_path_with(
+                agents[agent].start,
+                goal,
+                &table,
+                &options.cooperative,
+                distances,
+            )
+        };
+
+        let paths = (0..agents.len())
+            .map(|agent| plan(agent, &[]))
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut open = vec![self.constraint_node(Vec::new(), paths, options)];
+
+        for _ in 0..options.max_expansions {
+            let node = pop_focal(&mut open, options.suboptimality)?;
+
+            let Some(conflict) = self.conflicts(&node.paths, options).into_iter().next() else {
+                return Some(node.paths);
+            };
+
+            for constraint in conflict {
+                let agent = constraint.agent();
+
+                let mut constraints = node.constraints.clone();
+                constraints.push(constraint);
+
+                let Some(path) = plan(agent, &constraints) else {
+                    continue;
+                };
+
+                let mut paths = node.paths.clone();
+                paths[agent] = path;
+
+                open.push(self.constraint_node(constraints, paths, options));
+            }
+        }
+
+        None
+    }
+
+    fn constraint_node(
+        &self,
+        constraints: Vec<Constraint>,
+        paths: Vec<Path>,
+        options: &ConflictBasedOptions,
+    ) -> ConstraintNode {
+        ConstraintNode {
+            cost: paths.iter().map(|path| path.cost).sum(),
+            conflicts: self.conflicts(&paths, options).len(),
+            constraints,
+            paths,
+        }
+    }
+
+    /// Finds all conflicts between paths within the window. Every conflict is returned as the
+    /// constraints of its branches.
+    fn conflicts(&self, paths: &[Path], options: &ConflictBasedOptions) -> Vec<Vec<Constraint>> {
+        let agent_size = options.cooperative.agent_size;
+        let position = |agent: usize, time: u32| {
+            let links = &paths[agent].links;
+            links[(time as usize).min(links.len() - 1)]
+        };
+
+        let mut conflicts = Vec::new();
+
+        for time in 0..options.cooperative.window {
+            let mut agents_in_links: HashMap<_, Vec<_>> = HashMap::new();
+
+            for agent in 0..paths.len() {
+                agents_in_links
+                    .entry(position(agent, time))
+                    .or_default()
+                    .push(agent);
+            }
+
+            let mut vertex_conflicts = agents_in_links
+                .into_iter()
+                .filter(|(link, agents)| {
+                    agents.len() > self.agent_capacity(*link, agent_size) as usize
+                })
+                .collect::<Vec<_>>();
+
+            vertex_conflicts.sort_by_key(|(_, agents)| agents[0]);
+
+            for (link, agents) in vertex_conflicts {
+                conflicts.push(
+                    agents
+                        .into_iter()
+                        .map(|agent| Constraint::Vertex { agent, link, time })
+                        .collect(),
+                );
+            }
+
+            for a in 0..paths.len() {
+                for b in a + 1..paths.len() {
+                    let (from, to) = (position(a, time), position(a, time + 1));
+
+                    let swaps = from != to
+                        && position(b, time) == to
+                        && position(b, time + 1) == from
+                        && self
+                            .agent_capacity(from, agent_size)
+                            .min(self.agent_capacity(to, agent_size))
+                            == 1;
+
+                    if swaps {
+                        conflicts.push(vec![
+                            Constraint::Edge {
+                                agent: a,
+                                from,
+                                to,
+                                time,
+                            },
+                            Constraint::Edge {
+                                agent: b,
+                                from: to,
+                                to: from,
+                                time,
+                            },
+                        ]);
+                    }
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+/// Removes the node with the fewest conflicts among the nodes whose cost is within the
+/// suboptimality bound of the cheapest one.
+fn pop_focal(open: &mut Vec<ConstraintNode>, suboptimality: f32) -> Option<ConstraintNode> {
+    let lower_bound = open.iter().map(|node| node.cost).min_by(f32::total_cmp)?;
+
+    let bound = lower_bound * suboptimality.max(1.0) + 1e-4;
+
+    let (index, _) = open
+        .iter()
+        .enumerate()
+        .filter(|(_, node)| node.cost <= bound)
+        .min_by(|(_, a), (_, b)| {
+            a.conflicts
+                .cmp(&b.conflicts)
+                .then_with(|| a.cost.total_cmp(&b.cost))
+        })?;
+
+    Some(open.swap_remove(index))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::crossing_tunnels;
+
+    use super::*;
+
+    #[test]
+    fn test_conflict_free_paths_through_crossing() {
+        let tree = crossing_tunnels();
+
+        let node = |x: f32, y: f32| tree.find_node(Vec3::new(x, y, 4.5)).unwrap();
+
+        let agents = [
+            CooperativeAgent {
+                start: node(4.5, 0.5),
+                goal: node(4.5, 7.5),
+                priority: 0,
+            },
+            CooperativeAgent {
+                start: node(0.5, 4.5),
+                goal: node(7.5, 4.5),
+                priority: 0,
+            },
+        ];
+
+        let options = ConflictBasedOptions::default();
+
+        let independent = agents
+            .iter()
+            .map(|agent| {
+                tree.find_path(agent.start, agent.goal, &options.cooperative.path)
+                    .unwrap()
+                    .cost
+            })
+            .sum::<f32>();
+
+        let paths = tree.find_conflict_free_paths(&agents, &options).unwrap();
+        let cost = paths.iter().map(|path| path.cost).sum::<f32>();
+
+        assert!(tree.conflicts(&paths, &options).is_empty());
+        assert!((cost - independent - options.cooperative.wait_cost).abs() < 1e-4);
+
+        for (agent, path) in agents.iter().zip(&paths) {
+            assert_eq!(path.links.first(), Some(&agent.start));
+            assert_eq!(path.links.last(), Some(&agent.goal));
+        }
+
+        let bounded = ConflictBasedOptions {
+            suboptimality: 2.0,
+            ..Default::default()
+        };
+
+        let paths = tree.find_conflict_free_paths(&agents, &bounded).unwrap();
+
+        assert!(tree.conflicts(&paths, &bounded).is_empty());
+        assert!(paths.iter().map(|path| path.cost).sum::<f32>() <= cost * 2.0);
+
+        let capped = ConflictBasedOptions {
+            max_expansions: 0,
+            ..Default::default()
+        };
+
+        assert!(tree.find_conflict_free_paths(&agents, &capped).is_none());
+    }
+}
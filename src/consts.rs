@@ -338,6 +338,11 @@ pub const SUBNODE_POSITIONS: [(u8, u8, u8); 64] = [
     (3, 3, 3),
 ];
 
+/// Index of the opposite face for each of the 6 faces of a cube.
+///
+/// Uses the same face indexing as `NEIGHBOR_POSITION_OFFSETS`.
+pub const OPPOSITE_FACES: [usize; 6] = [2, 3, 0, 1, 5, 4];
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -708,4 +713,14 @@ mod tests {
 
         assert_eq!(SUBNODE_POSITIONS, positions.as_slice());
     }
+
+    #[test]
+    fn test_opposite_faces() {
+        for (face, opposite) in OPPOSITE_FACES.iter().enumerate() {
+            let a = NEIGHBOR_POSITION_OFFSETS[face];
+            let b = NEIGHBOR_POSITION_OFFSETS[*opposite];
+
+            assert_eq!((a.0 + b.0, a.1 + b.1, a.2 + b.2), (0, 0, 0));
+        }
+    }
 }
@@ -0,0 +1,487 @@
+// Resource: https://www.davidsilver.uk/wp-content/uploads/2020/03/coop-path-AIWisdom.pdf
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use crate::{
+    distance_field::LazyDistanceField, Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink,
+};
+
+/// Agent planned by [`SparseVoxelOctree::find_cooperative_paths`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CooperativeAgent {
+    /// Link the agent starts in at time step 0.
+    pub start: SparseVoxelOctreeLink,
+    /// Link the agent wants to reach.
+    pub goal: SparseVoxelOctreeLink,
+    /// Agents with higher priority are planned first and others avoid them.
+    pub priority: i32,
+}
+
+/// Options of the cooperative path search.
+#[derive(Debug, Clone)]
+pub struct CooperativeOptions {
+    /// Number of time steps in which agents avoid each other. Paths continue without
+    /// reservations after the window, so agents should replan before they reach its end.
+    pub window: u32,
+    /// Size of an agent in voxels along a single axis. A node can hold as many agents as fit
+    /// into it, but always at least one.
+    pub agent_size: u32,
+    /// Cost of waiting in a link for a single time step.
+    pub wait_cost: f32,
+    /// Options of the underlying path search.
+    pub path: PathOptions,
+}
+
+impl Default for CooperativeOptions {
+    fn default() -> Self {
+        Self {
+            window: 16,
+            agent_size: 1,
+            wait_cost: 1.0,
+            path: PathOptions::default(),
+        }
+    }
+}
+
+/// Links occupied by agents at discrete time steps.
+///
+/// Every step of a path takes one time step, moving to a neighboring link or waiting in the
+/// current one. The time step doesn't depend on the size of the links, so crossing a large node
+/// takes as long as crossing a single voxel. Agents should follow the paths at the pace of the
+/// time steps rather than at a constant speed.
+#[derive(Debug, Clone, Default)]
+pub struct ReservationTable {
+    /// Number of agents in a link at a time step.
+    links: HashMap<(SparseVoxelOctreeLink, u32), u32>,
+    /// Moves from a link to another one starting at a time step.
+    moves: HashMap<(SparseVoxelOctreeLink, SparseVoxelOctreeLink, u32), u32>,
+    /// Moves that are not allowed regardless of the capacity of the links.
+    forbidden_moves: HashSet<(SparseVoxelOctreeLink, SparseVoxelOctreeLink, u32)>,
+}
+
+impl ReservationTable {
+    /// Creates an empty table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes all reservations.
+    pub fn clear(&mut self) {
+        self.links.clear();
+        self.moves.clear();
+        self.forbidden_moves.clear();
+    }
+
+    /// Reserves the links of a path, the link at index `i` at time step `start_time + i`. The
+    /// last link is reserved until `end_time`, so agents at their goal aren't run over.
+    pub fn reserve_path(&mut self, path: &Path, start_time: u32, end_time: u32) {
+        let mut time = start_time;
+
+        for (index, link) in path.links.iter().enumerate() {
+            *self.links.entry((*link, time)).or_default() += 1;
+
+            if let Some(next) = path.links.get(index + 1) {
+                if next != link {
+                    *self.moves.entry((*link, *next, time)).or_default() += 1;
+                }
+            }
+
+            time += 1;
+        }
+
+        if let Some(last) = path.links.last() {
+            for time in time..end_time {
+                *self.links.entry((*last, time)).or_default() += 1;
+            }
+        }
+    }
+
+    /// Makes a link unavailable at a time step regardless of its capacity.
+    pub(crate) fn forbid(&mut self, link: SparseVoxelOctreeLink, time: u32) {
+        self.links.insert((link, time), u32::MAX);
+    }
+
+    /// Forbids moving from a link to another one starting at a time step.
+    pub(crate) fn forbid_move(
+        &mut self,
+        from: SparseVoxelOctreeLink,
+        to: SparseVoxelOctreeLink,
+        time: u32,
+    ) {
+        self.forbidden_moves.insert((from, to, time));
+    }
+
+    /// Number of agents reserved in a link at a time step.
+    #[must_use]
+    pub fn reservations(&self, link: SparseVoxelOctreeLink, time: u32) -> u32 {
+        self.links.get(&(link, time)).copied().unwrap_or_default()
+    }
+
+    /// Returns true if an agent can enter a link at a time step by moving from another link.
+    fn can_enter(
+        &self,
+        octree: &SparseVoxelOctree,
+        from: SparseVoxelOctreeLink,
+        to: SparseVoxelOctreeLink,
+        time: u32,
+        agent_size: u32,
+    ) -> bool {
+        let capacity = octree.agent_capacity(to, agent_size);
+
+        if self.reservations(to, time) >= capacity
+            || self.forbidden_moves.contains(&(from, to, time - 1))
+        {
+            return false;
+        }
+
+        // Agents can't swap places through links that hold a single agent.
+        from == to
+            || capacity.min(octree.agent_capacity(from, agent_size)) > 1
+            || !self.moves.contains_key(&(to, from, time - 1))
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Plans paths of multiple agents that don't collide with each other, using windowed
+    /// hierarchical cooperative A*.
+    ///
+    /// Agents are planned from the highest priority and each path is reserved in the table, so
+    /// the following agents wait or go around. Reservations already in the table, for example
+    /// of agents that are not replanned, are respected. Returns the paths in the order of
+    /// `agents`, where the link at index `i` is occupied at time step `i` and repeated links
+    /// mean waiting.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{
+    ///     CooperativeAgent, CooperativeOptions, ReservationTable, SparseVoxelOctreeBuilder,
+    /// };
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let a = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let b = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let agents = [
+    ///     CooperativeAgent { start: a, goal: b, priority: 0 },
+    ///     CooperativeAgent { start: b, goal: a, priority: 1 },
+    /// ];
+    ///
+    /// let mut table = ReservationTable::new();
+    /// let paths = octree.find_cooperative_paths(&agents, &mut table, &CooperativeOptions::default());
+    ///
+    /// assert_eq!(paths[0].as_ref().unwrap().links.last(), Some(&b));
+    /// assert_eq!(paths[1].as_ref().unwrap().links.last(), Some(&a));
+    /// ```
+    #[must_use]
+    pub fn find_cooperative_paths(
+        &self,
+        agents: &[CooperativeAgent],
+        table: &mut ReservationTable,
+        options: &CooperativeOptions,
+    ) -> Vec<Option<Path>> {
+        let mut order = (0..agents.len()).collect::<Vec<_>>();
+        order.sort_by_key(|index| -i64::from(agents[*index].priority));
+
+        let mut paths = vec![None; agents.len()];
+        let mut distances = HashMap::new();
+
+        for index in order {
+            let agent = agents[index];
+            let distances = distances
+                .entry(agent.goal)
+                .or_insert_with(|| LazyDistanceField::new(&[agent.goal]));
+            let path =
+                self.find_space_time_path_with(agent.start, agent.goal, table, options, distances);
+
+            if let Some(path) = &path {
+                table.reserve_path(path, 0, options.window);
+            }
+
+            paths[index] = path;
+        }
+
+        paths
+    }
+
+    /// Finds the cheapest path of a single agent that avoids the reservations in the table
+    /// during the window, starting at time step 0.
+    ///
+    /// The rest of the path after the window follows the cheapest path to the goal regardless
+    /// of the reservations. Costs to the goal used as the heuristic are computed only as far as
+    /// the search needs them. The cost of the returned path is the cost of all its moves and
+    /// waits, including the rest after the window. Returns `None` if there is no path.
+    #[must_use]
+    pub fn find_space_time_path(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        table: &ReservationTable,
+        options: &CooperativeOptions,
+    ) -> Option<Path> {
+        // Exact costs to the goal ignoring other agents serve as the heuristic.
+        let mut distances = LazyDistanceField::new(&[goal]);
+
+        self.find_space_time_path_with(start, goal, table, options, &mut distances)
+    }
+
+    /// Same as [`SparseVoxelOctree::find_space_time_path`] with distances to the goal shared
+    /// with other searches.
+    pub(crate) fn find_space_time_path_with(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        table: &ReservationTable,
+        options: &CooperativeOptions,
+        distances: &mut LazyDistanceField,
+    ) -> Option<Path> {
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::new();
+        let mut parents: HashMap<SpaceTime, SpaceTime> = HashMap::new();
+
+        costs.insert((start, 0), 0.0);
+        open.push(SpaceTimeNode {
+            estimate: distances.distance(self, start, &options.path)?,
+            cost: 0.0,
+            link: start,
+            time: 0,
+        });
+
+        while let Some(SpaceTimeNode {
+            cost, link, time, ..
+        }) = open.pop()
+        {
+            if costs.get(&(link, time)).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            let stays_at_goal = link == goal
+                && (time + 1..options.window).all(|time| {
+                    table.reservations(goal, time) < self.agent_capacity(goal, options.agent_size)
+                });
+
+            if time >= options.window || stays_at_goal {
+                let mut links = vec![link];
+                let mut current = (link, time);
+
+                while let Some(parent) = parents.get(&current) {
+                    links.push(parent.0);
+                    current = *parent;
+                }
+
+                links.reverse();
+
+                // Continue along the cheapest path after the window.
+                let mut cost = cost;
+                let mut current = link;
+
+                while let Some(next) = distances.next_step(current) {
+                    cost += self.edge_cost(current, next, &options.path)?;
+                    links.push(next);
+                    current = next;
+                }
+
+                return Some(self.path(links, cost));
+            }
+
+            let next_time = time + 1;
+            let mut steps = vec![(link, options.wait_cost)];
+
+            for successor in self.successors(link) {
+                if let Some(edge_cost) = self.edge_cost(link, successor, &options.path) {
+                    steps.push((successor, edge_cost));
+                }
+            }
+
+            for (next, edge_cost) in steps {
+                let Some(distance) = distances.distance(self, next, &options.path) else {
+                    continue;
+                };
+
+                if !table.can_enter(self, link, next, next_time, options.agent_size) {
+                    continue;
+                }
+
+                let next_cost = cost + edge_cost;
+
+                if costs
+                    .get(&(next, next_time))
+                    .is_some_and(|best| next_cost >= *best)
+                {
+                    continue;
+                }
+
+                costs.insert((next, next_time), next_cost);
+                parents.insert((next, next_time), (link, time));
+                open.push(SpaceTimeNode {
+                    estimate: next_cost + distance,
+                    cost: next_cost,
+                    link: next,
+                    time: next_time,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Number of agents of a size that fit into a link.
+    pub(crate) fn agent_capacity(&self, link: SparseVoxelOctreeLink, agent_size: u32) -> u32 {
+        (self.link_size(link) / agent_size.max(1)).pow(3).max(1)
+    }
+}
+
+/// Link at a time step.
+type SpaceTime = (SparseVoxelOctreeLink, u32);
+
+/// Entry of the open set of the space-time search ordered by the lowest estimate first.
+#[derive(Debug, Clone, Copy)]
+struct SpaceTimeNode {
+    estimate: f32,
+    cost: f32,
+    link: SparseVoxelOctreeLink,
+    time: u32,
+}
+
+impl PartialEq for SpaceTimeNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SpaceTimeNode {}
+
+impl PartialOrd for SpaceTimeNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SpaceTimeNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the lowest estimate first. Ties prefer later steps.
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| self.time.cmp(&other.time))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::crossing_tunnels;
+
+    use super::*;
+
+    #[test]
+    fn test_agents_wait_at_crossing() {
+        let tree = crossing_tunnels();
+
+        let node = |x: f32, y: f32| tree.find_node(Vec3::new(x, y, 4.5)).unwrap();
+
+        let agents = [
+            CooperativeAgent {
+                start: node(4.5, 0.5),
+                goal: node(4.5, 7.5),
+                priority: 0,
+            },
+            CooperativeAgent {
+                start: node(0.5, 4.5),
+                goal: node(7.5, 4.5),
+                priority: 1,
+            },
+        ];
+
+        let mut table = ReservationTable::new();
+        let options = CooperativeOptions::default();
+        let paths = tree
+            .find_cooperative_paths(&agents, &mut table, &options)
+            .into_iter()
+            .map(Option::unwrap)
+            .collect::<Vec<_>>();
+
+        // The agent with the higher priority goes straight through the crossing.
+        assert_eq!(paths[1].links.len(), 8);
+        assert_eq!(paths[0].links.last(), Some(&agents[0].goal));
+
+        let crossing = node(4.5, 4.5);
+        let times = paths
+            .iter()
+            .map(|path| {
+                path.links
+                    .iter()
+                    .position(|link| *link == crossing)
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_ne!(times[0], times[1]);
+        assert!(paths[0].links.windows(2).any(|pair| pair[0] == pair[1]));
+
+        for time in 0..options.window {
+            assert!(table.reservations(crossing, time) <= 1);
+        }
+
+        // A large air node holds both agents at once.
+        let open = tree.find_node(Vec3::new(-6.5, -6.5, -6.5)).unwrap();
+        assert!(tree.agent_capacity(open, options.agent_size) > 1);
+        assert_eq!(tree.agent_capacity(crossing, options.agent_size), 1);
+    }
+
+    #[test]
+    fn test_space_time_path_cost_after_window() {
+        let tree = crossing_tunnels();
+
+        let start = tree.find_node(Vec3::new(0.5, 4.5, 4.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(4.5, 7.5, 4.5)).unwrap();
+
+        // The agent waits in the tunnel during most of the short window.
+        let mut table = ReservationTable::new();
+        let blocked = tree.find_node(Vec3::new(2.5, 4.5, 4.5)).unwrap();
+
+        for time in 0..4 {
+            table.forbid(blocked, time);
+        }
+
+        let options = CooperativeOptions {
+            window: 4,
+            ..Default::default()
+        };
+
+        let path = tree
+            .find_space_time_path(start, goal, &table, &options)
+            .unwrap();
+
+        assert_eq!(path.links.last(), Some(&goal));
+        assert!(path.links.len() > options.window as usize);
+
+        let travelled = path
+            .links
+            .windows(2)
+            .map(|pair| {
+                if pair[0] == pair[1] {
+                    options.wait_cost
+                } else {
+                    tree.edge_cost(pair[0], pair[1], &options.path).unwrap()
+                }
+            })
+            .sum::<f32>();
+
+        assert!((path.cost - travelled).abs() < 1e-4);
+
+        let direct = tree.find_path(start, goal, &options.path).unwrap();
+
+        assert!(path.cost > direct.cost);
+    }
+}
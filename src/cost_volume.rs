@@ -0,0 +1,354 @@
+use std::collections::HashMap;
+
+use bevy_math::{IVec3, Vec3};
+
+use crate::{path_cache::Change, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Size of a cell of the spatial index of cost volumes in voxels.
+const CELL_SIZE_IN_VOXELS: f32 = 16.0;
+
+/// Volumes covering more cells are not put into the cells, but checked for every query.
+const MAX_CELLS_PER_VOLUME: f32 = 64.0;
+
+/// Identifier of a cost volume returned by [`SparseVoxelOctree::add_cost_volume`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct VolumeId(u32);
+
+/// Shape of a cost volume in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum VolumeShape {
+    /// Sphere given by its center and radius.
+    Sphere { center: Vec3, radius: f32 },
+    /// Axis aligned box given by its minimal and maximal corner.
+    Box { min: Vec3, max: Vec3 },
+}
+
+impl VolumeShape {
+    /// Axis aligned bounding box of the shape.
+    fn bounds(&self) -> (Vec3, Vec3) {
+        match *self {
+            VolumeShape::Sphere { center, radius } => (center - radius, center + radius),
+            VolumeShape::Box { min, max } => (min, max),
+        }
+    }
+
+    /// Returns true if the point is inside of the shape.
+    pub(crate) fn contains_point(&self, point: Vec3) -> bool {
+        match *self {
+            VolumeShape::Sphere { center, radius } => {
+                center.distance_squared(point) <= radius * radius
+            }
+            VolumeShape::Box { min, max } => point.cmpge(min).all() && point.cmple(max).all(),
+        }
+    }
+
+    /// Returns true if an axis aligned box is completely inside of the shape.
+    pub(crate) fn contains_box(&self, min: Vec3, max: Vec3) -> bool {
+        match *self {
+            VolumeShape::Sphere { center, .. } => {
+                // The farthest corner of the box from the center.
+                let corner = Vec3::select((center - min).cmpgt(max - center), min, max);
+
+                self.contains_point(corner)
+            }
+            VolumeShape::Box {
+                min: shape_min,
+                max: shape_max,
+            } => min.cmpge(shape_min).all() && max.cmple(shape_max).all(),
+        }
+    }
+
+    /// Returns true if the shape overlaps an axis aligned box.
+    pub(crate) fn intersects_box(&self, min: Vec3, max: Vec3) -> bool {
+        match *self {
+            VolumeShape::Sphere { center, radius } => {
+                center.clamp(min, max).distance_squared(center) < radius * radius
+            }
+            VolumeShape::Box {
+                min: shape_min,
+                max: shape_max,
+            } => shape_min.cmplt(max).all() && shape_max.cmpgt(min).all(),
+        }
+    }
+}
+
+/// Cost volumes of an octree together with a uniform grid over their bounds.
+#[derive(Debug, Clone)]
+pub(crate) struct CostVolumes {
+    volumes: HashMap<VolumeId, (VolumeShape, f32)>,
+    cells: HashMap<IVec3, Vec<VolumeId>>,
+    /// Volumes that are too large to be put into cells.
+    large: Vec<VolumeId>,
+    cell_size: f32,
+    next_id: u32,
+}
+
+impl CostVolumes {
+    pub(crate) fn new(voxel_size: f32) -> Self {
+        Self {
+            volumes: HashMap::new(),
+            cells: HashMap::new(),
+            large: Vec::new(),
+            cell_size: voxel_size * CELL_SIZE_IN_VOXELS,
+            next_id: 0,
+        }
+    }
+
+    fn insert(&mut self, shape: VolumeShape, multiplier: f32) -> VolumeId {
+        let id = VolumeId(self.next_id);
+        self.next_id += 1;
+
+        let (min, max) = shape.bounds();
+
+        match self.cells_between(min, max) {
+            Some(cells) => {
+                for cell in cells {
+                    self.cells.entry(cell).or_default().push(id);
+                }
+            }
+            None => self.large.push(id),
+        }
+
+        self.volumes.insert(id, (shape, multiplier));
+
+        id
+    }
+
+    fn remove(&mut self, id: VolumeId) -> bool {
+        let Some((shape, _)) = self.volumes.remove(&id) else {
+            return false;
+        };
+
+        let (min, max) = shape.bounds();
+
+        match self.cells_between(min, max) {
+            Some(cells) => {
+                for cell in cells {
+                    if let Some(ids) = self.cells.get_mut(&cell) {
+                        ids.retain(|other| *other != id);
+
+                        if ids.is_empty() {
+                            self.cells.remove(&cell);
+                        }
+                    }
+                }
+            }
+            None => self.large.retain(|other| *other != id),
+        }
+
+        true
+    }
+
+    /// Highest multiplier of the volumes overlapping a box or 1 if there is none.
+    fn multiplier(&self, min: Vec3, max: Vec3) -> f32 {
+        if self.volumes.is_empty() {
+            return 1.0;
+        }
+
+        let overlapping = |id: &VolumeId| {
+            self.volumes
+                .get(id)
+                .filter(|(shape, _)| shape.intersects_box(min, max))
+                .map(|(_, multiplier)| *multiplier)
+        };
+
+        let large = self.large.iter().filter_map(overlapping);
+
+        match self.cells_between(min, max) {
+            Some(cells) => cells
+                .iter()
+                .filter_map(|cell| self.cells.get(cell))
+                .flatten()
+                .filter_map(overlapping)
+                .chain(large)
+                .fold(1.0, f32::max),
+            // Checking all volumes is cheaper than visiting all cells of a large box.
+            None => self
+                .volumes
+                .values()
+                .filter(|(shape, _)| shape.intersects_box(min, max))
+                .map(|(_, multiplier)| *multiplier)
+                .fold(1.0, f32::max),
+        }
+    }
+
+    /// Cells overlapping a box or `None` if there are too many of them.
+    ///
+    /// The count is computed before converting to integer coordinates, so infinite or huge
+    /// boxes are treated as large instead of overflowing.
+    fn cells_between(&self, min: Vec3, max: Vec3) -> Option<Vec<IVec3>> {
+        let min = (min / self.cell_size).floor();
+        let max = (max / self.cell_size).floor();
+        let count = max - min + Vec3::ONE;
+
+        if !count.is_finite() || count.x * count.y * count.z > MAX_CELLS_PER_VOLUME {
+            return None;
+        }
+
+        let (min, max) = (min.as_ivec3(), max.as_ivec3());
+
+        let mut cells = Vec::new();
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    cells.push(IVec3::new(x, y, z));
+                }
+            }
+        }
+
+        Some(cells)
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Adds a volume that multiplies the cost of entering nodes overlapping it.
+    ///
+    /// Cost volumes bias paths away from dangerous areas without blocking them. Adding or
+    /// removing a volume doesn't change the nodes of the octree. Where volumes overlap, the
+    /// highest multiplier is used. Multipliers lower than 1 are raised to 1.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VolumeShape};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    ///
+    /// let explosion = octree.add_cost_volume(
+    ///     VolumeShape::Sphere { center: Vec3::ZERO, radius: 2.0 },
+    ///     10.0,
+    /// );
+    ///
+    /// assert!(octree.remove_cost_volume(explosion));
+    /// ```
+    pub fn add_cost_volume(&mut self, shape: VolumeShape, multiplier: f32) -> VolumeId {
+        self.record_change(Change::Volume(shape));
+
+        self.cost_volumes.insert(shape, multiplier.max(1.0))
+    }
+
+    /// Removes a cost volume. Returns false if there was no volume with the id.
+    pub fn remove_cost_volume(&mut self, id: VolumeId) -> bool {
+        let Some((shape, _)) = self.cost_volumes.volumes.get(&id).copied() else {
+            return false;
+        };
+
+        self.record_change(Change::Volume(shape));
+
+        self.cost_volumes.remove(id)
+    }
+
+    /// Cost multiplier of entering a link given by the cost volumes overlapping it.
+    pub(crate) fn cost_multiplier(&self, link: SparseVoxelOctreeLink) -> f32 {
+        let (min, max) = self.link_bounds(link);
+
+        self.cost_volumes.multiplier(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{PathOptions, SparseVoxelOctreeBuilder};
+
+    use super::*;
+
+    #[test]
+    fn test_cost_volume_index() {
+        let mut volumes = CostVolumes::new(1.0);
+
+        let small = volumes.insert(
+            VolumeShape::Box {
+                min: Vec3::ZERO,
+                max: Vec3::splat(4.0),
+            },
+            2.0,
+        );
+        let large = volumes.insert(
+            VolumeShape::Sphere {
+                center: Vec3::ZERO,
+                radius: 100.0,
+            },
+            3.0,
+        );
+
+        assert_eq!(volumes.large, vec![large]);
+        assert!((volumes.multiplier(Vec3::splat(1.0), Vec3::splat(2.0)) - 3.0).abs() < 1e-6);
+        assert!(volumes.remove(large));
+        assert!(!volumes.remove(large));
+        assert!((volumes.multiplier(Vec3::splat(1.0), Vec3::splat(2.0)) - 2.0).abs() < 1e-6);
+        assert!((volumes.multiplier(Vec3::splat(5.0), Vec3::splat(6.0)) - 1.0).abs() < 1e-6);
+        assert!(volumes.remove(small));
+        assert!(volumes.cells.is_empty());
+    }
+
+    #[test]
+    fn test_infinite_cost_volume() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let mut tree = builder.build();
+
+        let id = tree.add_cost_volume(
+            VolumeShape::Box {
+                min: Vec3::NEG_INFINITY,
+                max: Vec3::INFINITY,
+            },
+            2.0,
+        );
+
+        assert_eq!(tree.cost_volumes.large, vec![id]);
+
+        let link = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        assert!((tree.cost_multiplier(link) - 2.0).abs() < 1e-6);
+        assert!(tree.remove_cost_volume(id));
+        assert!(tree.cost_volumes.large.is_empty());
+        assert!((tree.cost_multiplier(link) - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_find_path_through_cost_volume() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        builder.add_mesh(crate::VoxelizedMesh::sphere(1.0, 1.0, IVec3::new(0, 6, 0)));
+
+        let mut tree = builder.build();
+        let node_count = tree.layers.iter().map(Vec::len).sum::<usize>();
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+
+        let direct = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        // A slab that can't be avoided.
+        let id = tree.add_cost_volume(
+            VolumeShape::Box {
+                min: Vec3::new(-1.0, -8.0, -8.0),
+                max: Vec3::new(1.0, 8.0, 8.0),
+            },
+            100.0,
+        );
+
+        let biased = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert_eq!(node_count, tree.layers.iter().map(Vec::len).sum::<usize>());
+        assert!(biased.cost > direct.cost);
+
+        tree.remove_cost_volume(id);
+
+        let restored = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert!((restored.cost - direct.cost).abs() < 1e-4);
+    }
+}
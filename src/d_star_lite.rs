@@ -0,0 +1,455 @@
+// Resource: http://idm-lab.org/bib/abstracts/papers/aaai02b.pdf
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap, HashSet},
+};
+
+use crate::{Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Incremental planner that repairs its path after the octree changes instead of searching
+/// from scratch, based on D* Lite.
+///
+/// The planner searches from the goal towards the start, so the start can move along the path
+/// while the costs computed so far stay valid. The planner doesn't borrow the octree, the same
+/// octree has to be passed to all of its methods.
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{DStarLite, PathOptions, SparseVoxelOctreeBuilder};
+/// use bevy_math::Vec3;
+///
+/// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+/// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+///
+/// let mut octree = builder.build();
+///
+/// let start = octree.find_node(Vec3::new(-6.5, -6.5, -6.5)).unwrap();
+/// let goal = octree.find_node(Vec3::new(6.5, -6.5, -6.5)).unwrap();
+///
+/// let mut planner = DStarLite::new(&octree, start, goal, PathOptions::default());
+///
+/// let path = planner.find_path(&octree).unwrap();
+///
+/// let changed = octree.set_voxel(Vec3::new(0.5, 6.5, 6.5), true).unwrap();
+/// planner.update(&octree, &changed);
+///
+/// let repaired = planner.find_path(&octree).unwrap();
+///
+/// assert_eq!(repaired.links.last(), Some(&goal));
+/// assert!(repaired.cost >= path.cost);
+/// ```
+#[derive(Debug, Clone)]
+pub struct DStarLite {
+    start: SparseVoxelOctreeLink,
+    goal: SparseVoxelOctreeLink,
+    /// Start at the time the key modifier was last changed.
+    last_start: SparseVoxelOctreeLink,
+    /// Key modifier accumulating the heuristic between the starts the planner was used from.
+    key_modifier: f32,
+    options: PathOptions,
+    /// Cost to the goal computed by the last expansion of a link.
+    costs: HashMap<SparseVoxelOctreeLink, f32>,
+    /// One step lookahead of the cost to the goal.
+    lookahead: HashMap<SparseVoxelOctreeLink, f32>,
+    open: BinaryHeap<QueueEntry>,
+    /// Current key of the links in the open set. Entries of the heap with other keys are stale.
+    open_keys: HashMap<SparseVoxelOctreeLink, Key>,
+}
+
+impl DStarLite {
+    /// Creates a planner searching for a path between two links. No search is done until
+    /// [`DStarLite::find_path`] is called.
+    #[must_use]
+    pub fn new(
+        octree: &SparseVoxelOctree,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: PathOptions,
+    ) -> Self {
+        let mut planner = Self {
+            start,
+            goal,
+            last_start: start,
+            key_modifier: 0.0,
+            options,
+            costs: HashMap::new(),
+            lookahead: HashMap::new(),
+            open: BinaryHeap::new(),
+            open_keys: HashMap::new(),
+        };
+
+        planner.lookahead.insert(goal, 0.0);
+        planner.insert(octree, goal);
+
+        planner
+    }
+
+    /// Start of the planned path.
+    #[must_use]
+    pub fn start(&self) -> SparseVoxelOctreeLink {
+        self.start
+    }
+
+    /// Goal of the planned path.
+    #[must_use]
+    pub fn goal(&self) -> SparseVoxelOctreeLink {
+        self.goal
+    }
+
+    /// Moves the start, for example after the agent advanced along the path.
+    ///
+    /// The costs computed so far are kept, so moving the start is cheap.
+    pub fn set_start(&mut self, octree: &SparseVoxelOctree, start: SparseVoxelOctreeLink) {
+        self.key_modifier += octree.heuristic(self.last_start, start);
+        self.last_start = start;
+        self.start = start;
+    }
+
+    /// Notifies the planner about links that changed, such as the links returned by
+    /// [`SparseVoxelOctree::set_voxel`] or [`crate::BlockerChange::links`].
+    ///
+    /// Only the links around the changes are updated, the path is repaired by the next call of
+    /// [`DStarLite::find_path`]. Links of subdivided nodes are replaced by their children.
+    ///
+    /// If the node of the start or of the goal was subdivided or merged, it's replaced by the
+    /// cell containing its center. A new goal invalidates all costs, so the next call of
+    /// [`DStarLite::find_path`] searches from scratch.
+    pub fn update(&mut self, octree: &SparseVoxelOctree, changed: &[SparseVoxelOctreeLink]) {
+        if let Some(start) = octree
+            .cell_at_center(self.start)
+            .filter(|start| *start != self.start)
+        {
+            self.set_start(octree, start);
+        }
+
+        if let Some(goal) = octree
+            .cell_at_center(self.goal)
+            .filter(|goal| *goal != self.goal)
+        {
+            *self = Self::new(octree, self.start, goal, std::mem::take(&mut self.options));
+
+            return;
+        }
+
+        let mut affected = HashSet::new();
+
+        for link in changed {
+            affected.insert(*link);
+            affected.extend(octree.predecessors(*link));
+
+            if link.subnode_index.is_none() {
+                for cell in octree.cells_within(*link) {
+                    if cell != *link {
+                        affected.insert(cell);
+                        affected.extend(octree.predecessors(cell));
+                    }
+                }
+            }
+        }
+
+        for link in affected {
+            self.update_link(octree, link);
+        }
+    }
+
+    /// Computes or repairs the cheapest path from the start to the goal.
+    ///
+    /// Returns `None` if there is no path.
+    pub fn find_path(&mut self, octree: &SparseVoxelOctree) -> Option<Path> {
+        self.compute_costs(octree);
+
+        if self.cost(self.start).is_infinite() {
+            return None;
+        }
+
+        let mut links = vec![self.start];
+        let mut cost = 0.0;
+        let mut current = self.start;
+
+        while current != self.goal {
+            // A path can't be longer than the number of links with a known cost.
+            if links.len() > self.costs.len() {
+                return None;
+            }
+
+            let (next, edge_cost) = octree
+                .successors(current)
+                .into_iter()
+                .filter_map(|successor| {
+                    let edge_cost = octree.edge_cost(current, successor, &self.options)?;
+
+                    Some((successor, edge_cost, edge_cost + self.cost(successor)))
+                })
+                .filter(|(_, _, total)| total.is_finite())
+                .min_by(|a, b| a.2.total_cmp(&b.2))
+                .map(|(successor, edge_cost, _)| (successor, edge_cost))?;
+
+            links.push(next);
+            cost += edge_cost;
+            current = next;
+        }
+
+        Some(octree.path(links, cost))
+    }
+
+    /// Expands links until the cost of the start is consistent.
+    fn compute_costs(&mut self, octree: &SparseVoxelOctree) {
+        while let Some(QueueEntry { key, link }) = self.pop_valid() {
+            let start_key = self.key(octree, self.start);
+
+            if compare_keys(key, start_key) != Ordering::Less && !self.is_inconsistent(self.start) {
+                self.open.push(QueueEntry { key, link });
+                break;
+            }
+
+            self.open_keys.remove(&link);
+
+            let new_key = self.key(octree, link);
+
+            if compare_keys(key, new_key) == Ordering::Less {
+                self.push(link, new_key);
+            } else if self.cost(link) > self.lookahead(link) {
+                self.costs.insert(link, self.lookahead(link));
+
+                for predecessor in octree.predecessors(link) {
+                    self.update_link(octree, predecessor);
+                }
+            } else {
+                self.costs.insert(link, f32::INFINITY);
+                self.update_link(octree, link);
+
+                for predecessor in octree.predecessors(link) {
+                    self.update_link(octree, predecessor);
+                }
+            }
+        }
+    }
+
+    /// Recomputes the lookahead of a link and puts it into the open set if it's inconsistent.
+    fn update_link(&mut self, octree: &SparseVoxelOctree, link: SparseVoxelOctreeLink) {
+        if link != self.goal {
+            let lookahead = if octree.is_cell(link) {
+                octree
+                    .successors(link)
+                    .into_iter()
+                    .filter_map(|successor| {
+                        Some(
+                            octree.edge_cost(link, successor, &self.options)?
+                                + self.cost(successor),
+                        )
+                    })
+                    .fold(f32::INFINITY, f32::min)
+            } else {
+                f32::INFINITY
+            };
+
+            self.lookahead.insert(link, lookahead);
+        }
+
+        self.open_keys.remove(&link);
+
+        if self.is_inconsistent(link) {
+            self.insert(octree, link);
+        }
+    }
+
+    fn insert(&mut self, octree: &SparseVoxelOctree, link: SparseVoxelOctreeLink) {
+        let key = self.key(octree, link);
+
+        self.push(link, key);
+    }
+
+    fn push(&mut self, link: SparseVoxelOctreeLink, key: Key) {
+        self.open_keys.insert(link, key);
+        self.open.push(QueueEntry { key, link });
+    }
+
+    /// Pops the entry with the lowest key, skipping stale entries.
+    fn pop_valid(&mut self) -> Option<QueueEntry> {
+        while let Some(entry) = self.open.pop() {
+            if self.open_keys.get(&entry.link) == Some(&entry.key) {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+
+    fn key(&self, octree: &SparseVoxelOctree, link: SparseVoxelOctreeLink) -> Key {
+        let cost = self.cost(link).min(self.lookahead(link));
+
+        [
+            cost + octree.heuristic(self.start, link) + self.key_modifier,
+            cost,
+        ]
+    }
+
+    /// Returns true if the cost of a link differs from its lookahead.
+    #[allow(clippy::float_cmp)]
+    fn is_inconsistent(&self, link: SparseVoxelOctreeLink) -> bool {
+        self.cost(link) != self.lookahead(link)
+    }
+
+    #[inline]
+    fn cost(&self, link: SparseVoxelOctreeLink) -> f32 {
+        self.costs.get(&link).copied().unwrap_or(f32::INFINITY)
+    }
+
+    #[inline]
+    fn lookahead(&self, link: SparseVoxelOctreeLink) -> f32 {
+        self.lookahead.get(&link).copied().unwrap_or(f32::INFINITY)
+    }
+}
+
+/// Priority of a link in the open set, compared lexicographically.
+type Key = [f32; 2];
+
+fn compare_keys(a: Key, b: Key) -> Ordering {
+    a[0].total_cmp(&b[0]).then_with(|| a[1].total_cmp(&b[1]))
+}
+
+/// Entry of the open set ordered by the lowest key first.
+#[derive(Debug, Clone, Copy)]
+struct QueueEntry {
+    key: Key,
+    link: SparseVoxelOctreeLink,
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueueEntry {}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the lowest key first.
+        compare_keys(other.key, self.key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::wall_with_door;
+
+    use super::*;
+
+    fn ends(tree: &SparseVoxelOctree) -> (SparseVoxelOctreeLink, SparseVoxelOctreeLink) {
+        (
+            tree.find_node(Vec3::new(-4.5, 0.5, 0.5)).unwrap(),
+            tree.find_node(Vec3::new(4.5, 0.5, 0.5)).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_repair_path_after_subdivision() {
+        let (mut tree, _) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        let mut planner = DStarLite::new(&tree, start, goal, PathOptions::default());
+
+        let through_hole = planner.find_path(&tree).unwrap();
+        let expected = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert!((through_hole.cost - expected.cost).abs() < 1e-4);
+
+        // Partially closing the hole subdivides nodes around it.
+        let changed = tree.set_voxel(Vec3::new(0.5, 0.5, 0.5), true).unwrap();
+        planner.update(&tree, &changed);
+
+        let (start, goal) = ends(&tree);
+
+        assert_eq!(planner.goal(), goal);
+        planner.set_start(&tree, start);
+
+        let repaired = planner.find_path(&tree).unwrap();
+        let expected = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert!((repaired.cost - expected.cost).abs() < 1e-4);
+
+        for pair in repaired.links.windows(2) {
+            assert!(tree.successors(pair[0]).contains(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_repair_path_after_blocker_toggle() {
+        let (mut tree, door) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        let mut planner = DStarLite::new(&tree, start, goal, PathOptions::default());
+        let open = planner.find_path(&tree).unwrap();
+
+        // Closing the door blocks the only way through the wall.
+        let change = tree.set_blocker_enabled(door, true).unwrap();
+        planner.update(&tree, &change.links);
+
+        assert!(planner.find_path(&tree).is_none());
+
+        let change = tree.set_blocker_enabled(door, false).unwrap();
+        planner.update(&tree, &change.links);
+
+        let reopened = planner.find_path(&tree).unwrap();
+
+        assert!((reopened.cost - open.cost).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_resolve_subdivided_goal() {
+        let (mut tree, _) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        let mut planner = DStarLite::new(&tree, start, goal, PathOptions::default());
+        planner.find_path(&tree).unwrap();
+
+        // Filling a voxel next to the goal subdivides its node.
+        let changed = tree.set_voxel(Vec3::new(5.5, 1.5, 1.5), true).unwrap();
+        planner.update(&tree, &changed);
+
+        assert!(!tree.is_cell(goal));
+
+        let goal = tree.find_node(tree.node_position(goal)).unwrap();
+
+        assert_eq!(planner.goal(), goal);
+
+        let path = planner.find_path(&tree).unwrap();
+        let expected = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert_eq!(path.links.last(), Some(&goal));
+        assert!((path.cost - expected.cost).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_move_start_along_path() {
+        let (tree, _) = wall_with_door();
+        let (start, goal) = ends(&tree);
+
+        let mut planner = DStarLite::new(&tree, start, goal, PathOptions::default());
+        let path = planner.find_path(&tree).unwrap();
+
+        // Moving the start along the path keeps the rest of the path.
+        planner.set_start(&tree, path.links[3]);
+
+        let moved = planner.find_path(&tree).unwrap();
+
+        assert_eq!(moved.links[..], path.links[3..]);
+    }
+}
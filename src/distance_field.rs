@@ -0,0 +1,298 @@
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{path_finding::OpenNode, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Travel costs from reachable links to the nearest of a set of sources, also known as a
+/// Dijkstra map.
+///
+/// Created by [`SparseVoxelOctree::distance_field`]. Besides the cost, every link stores the
+/// next step towards its nearest source, so following the field doesn't need any search. The
+/// field isn't updated when the octree changes.
+#[derive(Debug, Clone, Default)]
+pub struct DistanceField {
+    distances: HashMap<SparseVoxelOctreeLink, f32>,
+    next_steps: HashMap<SparseVoxelOctreeLink, SparseVoxelOctreeLink>,
+}
+
+impl DistanceField {
+    /// Cost of travelling from a link to the nearest source or `None` if no source is reachable
+    /// within the maximal distance.
+    #[must_use]
+    pub fn distance(&self, link: SparseVoxelOctreeLink) -> Option<f32> {
+        self.distances.get(&link).copied()
+    }
+
+    /// Next link on the cheapest path from a link to its nearest source. Returns `None` for
+    /// sources and unreachable links.
+    #[must_use]
+    pub fn next_step(&self, link: SparseVoxelOctreeLink) -> Option<SparseVoxelOctreeLink> {
+        self.next_steps.get(&link).copied()
+    }
+
+    /// Iterates over all reached links and their distances.
+    pub fn iter(&self) -> impl Iterator<Item = (SparseVoxelOctreeLink, f32)> + '_ {
+        self.distances
+            .iter()
+            .map(|(link, distance)| (*link, *distance))
+    }
+
+    /// Number of reached links.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.distances.len()
+    }
+
+    /// Returns true if no link was reached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.distances.is_empty()
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Computes the travel cost from every link to the nearest of the sources using Dijkstra's
+    /// algorithm.
+    ///
+    /// Costs are the same as the costs of [`SparseVoxelOctree::find_path`] towards the source.
+    /// Links farther than `max_distance` from all sources are not included, pass
+    /// `f32::INFINITY` to cover the whole reachable space.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let threat = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let field = octree.distance_field(&[threat], f32::INFINITY, &PathOptions::default());
+    ///
+    /// let agent = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// assert_eq!(field.distance(threat), Some(0.0));
+    /// assert!(field.distance(agent).unwrap() > 0.0);
+    /// assert_eq!(field.next_step(agent), Some(threat));
+    /// ```
+    #[must_use]
+    pub fn distance_field(
+        &self,
+        sources: &[SparseVoxelOctreeLink],
+        max_distance: f32,
+        options: &PathOptions,
+    ) -> DistanceField {
+        let mut lazy = LazyDistanceField::new(sources);
+
+        while lazy.settle_next(self, max_distance, options).is_some() {}
+
+        lazy.field
+    }
+}
+
+/// Distances to a set of sources computed on demand.
+///
+/// The Dijkstra search of [`SparseVoxelOctree::distance_field`] is resumed only until the queried
+/// link is settled, so searches that need the distances of a few links near a far away source
+/// don't pay for the whole field, and later queries reuse the settled links.
+#[derive(Debug, Clone)]
+pub(crate) struct LazyDistanceField {
+    field: DistanceField,
+    open: BinaryHeap<OpenNode>,
+    /// Cost of the last settled link. Links reached for at most this cost are final.
+    radius: f32,
+}
+
+impl LazyDistanceField {
+    pub(crate) fn new(sources: &[SparseVoxelOctreeLink]) -> Self {
+        let mut lazy = Self {
+            field: DistanceField::default(),
+            open: BinaryHeap::new(),
+            radius: 0.0,
+        };
+
+        for source in sources {
+            lazy.field.distances.insert(*source, 0.0);
+            lazy.open.push(OpenNode {
+                estimate: 0.0,
+                cost: 0.0,
+                link: *source,
+            });
+        }
+
+        lazy
+    }
+
+    /// Cost of travelling from a link to the nearest source, resuming the search until the link
+    /// is settled. Returns `None` if no source is reachable.
+    pub(crate) fn distance(
+        &mut self,
+        octree: &SparseVoxelOctree,
+        link: SparseVoxelOctreeLink,
+        options: &PathOptions,
+    ) -> Option<f32> {
+        loop {
+            if let Some(distance) = self.field.distance(link) {
+                if distance <= self.radius {
+                    return Some(distance);
+                }
+            }
+
+            self.settle_next(octree, f32::INFINITY, options)?;
+        }
+    }
+
+    /// Next link on the cheapest path from a settled link to its nearest source.
+    pub(crate) fn next_step(&self, link: SparseVoxelOctreeLink) -> Option<SparseVoxelOctreeLink> {
+        self.field.next_step(link)
+    }
+
+    /// Settles the closest open link and reaches its predecessors. Returns `None` once all
+    /// links within `max_distance` are settled.
+    fn settle_next(
+        &mut self,
+        octree: &SparseVoxelOctree,
+        max_distance: f32,
+        options: &PathOptions,
+    ) -> Option<SparseVoxelOctreeLink> {
+        let OpenNode { cost, link, .. } = loop {
+            let node = self.open.pop()?;
+
+            if self
+                .field
+                .distances
+                .get(&node.link)
+                .map_or(true, |best| node.cost <= *best)
+            {
+                break node;
+            }
+        };
+
+        self.radius = cost;
+
+        // Edges are followed backwards, as the cost of an edge depends on the entered link.
+        for predecessor in octree.predecessors(link) {
+            let Some(edge_cost) = octree.edge_cost(predecessor, link, options) else {
+                continue;
+            };
+
+            let predecessor_cost = cost + edge_cost;
+
+            if predecessor_cost > max_distance
+                || self
+                    .field
+                    .distances
+                    .get(&predecessor)
+                    .is_some_and(|best| predecessor_cost >= *best)
+            {
+                continue;
+            }
+
+            self.field.distances.insert(predecessor, predecessor_cost);
+            self.field.next_steps.insert(predecessor, link);
+            self.open.push(OpenNode {
+                estimate: predecessor_cost,
+                cost: predecessor_cost,
+                link: predecessor,
+            });
+        }
+
+        Some(link)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::sphere;
+
+    use super::*;
+
+    #[test]
+    fn test_distance_field_from_two_sources() {
+        let tree = sphere(3.0);
+
+        let sources = [
+            tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap(),
+            tree.find_node(Vec3::new(6.5, 6.5, 6.5)).unwrap(),
+        ];
+
+        let field = tree.distance_field(&sources, f32::INFINITY, &PathOptions::default());
+
+        assert!(!field.is_empty());
+
+        for (link, distance) in field.iter().take(50) {
+            let expected = sources
+                .iter()
+                .filter_map(|source| tree.find_path(link, *source, &PathOptions::default()))
+                .map(|path| path.cost)
+                .fold(f32::INFINITY, f32::min);
+
+            assert!((distance - expected).abs() < 1e-3);
+
+            // Following the next steps leads to a source with decreasing distances.
+            let mut current = link;
+
+            while let Some(next) = field.next_step(current) {
+                assert!(tree.successors(current).contains(&next));
+                assert!(field.distance(next).unwrap() < field.distance(current).unwrap());
+                current = next;
+            }
+
+            assert!(sources.contains(&current));
+        }
+    }
+
+    #[test]
+    fn test_distance_field_with_max_distance() {
+        let tree = sphere(3.0);
+
+        let sources = [tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap()];
+
+        let field = tree.distance_field(&sources, f32::INFINITY, &PathOptions::default());
+        let limited = tree.distance_field(&sources, 3.0, &PathOptions::default());
+
+        assert!(limited.len() < field.len());
+        assert!(limited.iter().all(|(_, distance)| distance <= 3.0));
+    }
+
+    #[test]
+    fn test_lazy_distance_field() {
+        let tree = sphere(3.0);
+        let options = PathOptions::default();
+
+        let goal = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let near = tree.find_node(Vec3::new(-5.5, 0.5, 0.5)).unwrap();
+        let far = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+
+        let field = tree.distance_field(&[goal], f32::INFINITY, &options);
+        let mut lazy = LazyDistanceField::new(&[goal]);
+
+        assert_eq!(lazy.distance(&tree, near, &options), field.distance(near));
+
+        // Only the links around the goal were settled.
+        assert!(lazy.field.len() < field.len() / 2);
+
+        assert_eq!(lazy.distance(&tree, far, &options), field.distance(far));
+        assert_eq!(lazy.next_step(far), field.next_step(far));
+    }
+
+    #[test]
+    fn test_lazy_distance_field_to_unreachable_link() {
+        let tree = sphere(3.0);
+        let options = PathOptions::default();
+
+        let goal = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let inside = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        let field = tree.distance_field(&[goal], f32::INFINITY, &options);
+        let mut lazy = LazyDistanceField::new(&[goal]);
+
+        // Occupied links are never reached, so the whole field is settled.
+        assert_eq!(lazy.distance(&tree, inside, &options), None);
+        assert_eq!(lazy.field.len(), field.len());
+    }
+}
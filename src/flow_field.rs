@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+
+use bevy_math::Vec3;
+
+use crate::{PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Directions towards a shared goal for every link that can reach it.
+///
+/// Created by [`SparseVoxelOctree::flow_field`]. Every link stores a single quantized
+/// direction, so large air nodes cover big parts of open space with a single entry. The field
+/// isn't updated when the octree changes.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    goal: Vec3,
+    goal_link: SparseVoxelOctreeLink,
+    /// Direction towards the next link on the path to the goal, scaled to the range of `i8`.
+    directions: HashMap<SparseVoxelOctreeLink, [i8; 3]>,
+}
+
+impl FlowField {
+    /// Goal the field leads to.
+    #[must_use]
+    pub fn goal(&self) -> Vec3 {
+        self.goal
+    }
+
+    /// Number of links with a direction.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.directions.len()
+    }
+
+    /// Returns true if no link can reach the goal.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.directions.is_empty()
+    }
+
+    /// Direction stored for a link or `None` if the link can't reach the goal.
+    #[must_use]
+    pub fn direction(&self, link: SparseVoxelOctreeLink) -> Option<Vec3> {
+        self.directions.get(&link).map(|direction| {
+            Vec3::new(
+                f32::from(direction[0]),
+                f32::from(direction[1]),
+                f32::from(direction[2]),
+            )
+            .normalize_or_zero()
+        })
+    }
+
+    /// Samples the direction towards the goal at a worldspace position.
+    ///
+    /// Near the faces of a node, the direction is blended with the direction of the node on the
+    /// other side of the face, so agents don't turn abruptly when crossing into another node.
+    /// Inside of the node of the goal the direction points directly at the goal. Returns
+    /// `Vec3::ZERO` if the position can't reach the goal.
+    #[must_use]
+    pub fn flow_direction(&self, octree: &SparseVoxelOctree, position: Vec3) -> Vec3 {
+        let Some(link) = octree.find_node(position) else {
+            return Vec3::ZERO;
+        };
+
+        let Some(direction) = self.sample(link, position) else {
+            return Vec3::ZERO;
+        };
+
+        let (min, max) = octree.link_bounds(link);
+        let half_size = (max - min) / 2.0;
+        let center = min + half_size;
+
+        let mut blended = direction;
+
+        for axis in 0..3 {
+            let offset = position[axis] - center[axis];
+
+            // 0 in the center of the node and 0.5 at its face.
+            let weight = (offset.abs() / half_size[axis] / 2.0).min(0.5);
+
+            if weight <= 0.0 {
+                continue;
+            }
+
+            let mut across = position;
+            across[axis] =
+                center[axis] + offset.signum() * (half_size[axis] + octree.voxel_size / 2.0);
+
+            let neighbor_direction = octree
+                .find_node(across)
+                .and_then(|neighbor| self.sample(neighbor, position));
+
+            if let Some(neighbor_direction) = neighbor_direction {
+                // Equal weights of both nodes at the face.
+                blended += neighbor_direction * (weight / (1.0 - weight));
+            }
+        }
+
+        blended.try_normalize().unwrap_or(direction)
+    }
+
+    /// Direction of a link at a position, pointing at the goal inside of the node of the goal.
+    fn sample(&self, link: SparseVoxelOctreeLink, position: Vec3) -> Option<Vec3> {
+        if link == self.goal_link {
+            return Some((self.goal - position).normalize_or_zero());
+        }
+
+        self.direction(link)
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Computes a flow field leading every link within `max_distance` to a goal.
+    ///
+    /// Distances are computed the same way as in [`SparseVoxelOctree::distance_field`] and every
+    /// link points towards the next link on its cheapest path to the goal. Returns `None` if
+    /// the goal is outside of the octree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let rally_point = Vec3::new(6.5, 0.5, 0.5);
+    /// let field = octree
+    ///     .flow_field(rally_point, f32::INFINITY, &PathOptions::default())
+    ///     .unwrap();
+    ///
+    /// let direction = field.flow_direction(&octree, Vec3::new(-6.5, 0.5, 0.5));
+    ///
+    /// assert!(direction.x > 0.0);
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn flow_field(
+        &self,
+        goal: Vec3,
+        max_distance: f32,
+        options: &PathOptions,
+    ) -> Option<FlowField> {
+        let goal_link = self.find_node(goal)?;
+        let distances = self.distance_field(&[goal_link], max_distance, options);
+
+        let directions = distances
+            .iter()
+            .map(|(link, _)| {
+                let direction = distances.next_step(link).map_or(Vec3::ZERO, |next| {
+                    (self.flow_target(link, next) - self.node_position(link)).normalize_or_zero()
+                });
+
+                let quantized = (direction * f32::from(i8::MAX)).round();
+
+                (
+                    link,
+                    [quantized.x as i8, quantized.y as i8, quantized.z as i8],
+                )
+            })
+            .collect();
+
+        Some(FlowField {
+            goal,
+            goal_link,
+            directions,
+        })
+    }
+
+    /// Point of the face shared with the next link that is closest to the center of a link, or
+    /// the center of the next link if they don't share a face, e.g. across an off-mesh link.
+    fn flow_target(&self, link: SparseVoxelOctreeLink, next: SparseVoxelOctreeLink) -> Vec3 {
+        let (min, max) = self.link_bounds(link);
+        let (next_min, next_max) = self.link_bounds(next);
+        let (face_min, face_max) = (min.max(next_min), max.min(next_max));
+
+        if face_min.cmpgt(face_max).any() {
+            return self.node_position(next);
+        }
+
+        self.node_position(link).clamp(face_min, face_max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::IVec3;
+
+    use crate::{test_fixtures::sphere, SparseVoxelOctreeBuilder, VoxelState, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_follow_flow_field_around_sphere() {
+        let tree = sphere(3.0);
+        let goal = Vec3::new(6.5, 0.5, 0.5);
+
+        let field = tree
+            .flow_field(goal, f32::INFINITY, &PathOptions::default())
+            .unwrap();
+
+        // Variable node sizes need far fewer entries than voxels.
+        assert!(field.len() < 16 * 16 * 16 / 4);
+        assert_eq!(field.goal(), goal);
+        assert_eq!(
+            field.flow_direction(&tree, Vec3::new(0.5, 0.5, 0.5)),
+            Vec3::ZERO
+        );
+
+        let mut position = Vec3::new(-6.5, 0.5, 0.5);
+
+        for _ in 0..200 {
+            let direction = field.flow_direction(&tree, position);
+
+            assert!((direction.length() - 1.0).abs() < 1e-3);
+            assert_ne!(tree.voxel_state(position), Some(VoxelState::Occupied));
+
+            position += direction * 0.25;
+
+            if position.distance(goal) < 0.5 {
+                break;
+            }
+        }
+
+        assert!(position.distance(goal) < 0.5);
+    }
+
+    #[test]
+    fn test_flow_field_across_node_sizes() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::sphere(1.0, 1.0, IVec3::new(5, 5, 5)));
+        builder.set_bounds(Vec3::new(-16.0, -16.0, -16.0), Vec3::new(16.0, 16.0, 16.0));
+
+        let tree = builder.build();
+        let goal = Vec3::new(6.5, 3.5, 5.5);
+        let options = PathOptions::default();
+
+        let field = tree.flow_field(goal, f32::INFINITY, &options).unwrap();
+        let distances = tree.distance_field(&[field.goal_link], f32::INFINITY, &options);
+
+        // Every arrow points into the next link, whether it is larger or smaller.
+        let mut smaller_next = 0;
+
+        for (link, _) in distances.iter() {
+            let Some(next) = distances.next_step(link) else {
+                continue;
+            };
+
+            let direction = field.direction(link).unwrap();
+            let (min, max) = tree.link_bounds(link);
+            let (next_min, next_max) = tree.link_bounds(next);
+
+            if (next_max - next_min).x < (max - min).x {
+                smaller_next += 1;
+            }
+
+            let towards_next = tree.node_position(next) - tree.node_position(link);
+
+            assert!(direction.dot(towards_next) > 0.0, "{link:?} -> {next:?}");
+        }
+
+        assert!(smaller_next > 0);
+
+        // Agents starting in large air nodes reach the goal next to the small nodes around the
+        // sphere.
+        for start in [
+            Vec3::new(-14.0, -14.0, -14.0),
+            Vec3::new(14.0, 14.0, 14.0),
+            Vec3::new(-14.0, 12.0, 3.0),
+        ] {
+            let mut position = start;
+
+            for _ in 0..400 {
+                position += field.flow_direction(&tree, position) * 0.25;
+
+                if position.distance(goal) < 0.5 {
+                    break;
+                }
+            }
+
+            assert!(position.distance(goal) < 0.5, "{start} ended at {position}");
+        }
+    }
+}
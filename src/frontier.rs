@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+
+use bevy_math::Vec3;
+
+use crate::{SparseVoxelOctree, SparseVoxelOctreeLink, VoxelState};
+
+/// Cluster of free nodes adjacent to unknown space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frontier {
+    /// Free links of the cluster that have at least one unknown neighbor.
+    pub links: Vec<SparseVoxelOctreeLink>,
+    /// Volume weighted center of the cluster in world space.
+    pub centroid: Vec3,
+}
+
+impl SparseVoxelOctree {
+    /// Finds clusters of free nodes that neighbor unknown space.
+    ///
+    /// Frontier nodes that share a face belong to the same cluster, off-mesh links and blockers
+    /// don't join clusters. Flying to the centroid of a frontier reveals previously unobserved
+    /// space, which is the basis of autonomous exploration. The links of a frontier and the
+    /// frontiers are sorted, so the result is the same for the same octree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VoxelizedMesh, VoxelState};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.set_unknown_by_default(true);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    /// builder.add_mesh_with_state(
+    ///     VoxelizedMesh::new(vec![UVec3::new(0, 0, 0)], 1.0, IVec3::ZERO),
+    ///     VoxelState::Free,
+    /// );
+    ///
+    /// let octree = builder.build();
+    /// let frontiers = octree.frontiers();
+    ///
+    /// assert_eq!(frontiers.len(), 1);
+    /// assert_eq!(frontiers[0].centroid, Vec3::new(0.5, 0.5, 0.5));
+    /// ```
+    #[must_use]
+    pub fn frontiers(&self) -> Vec<Frontier> {
+        let frontier_links = self
+            .cells()
+            .into_iter()
+            .filter(|link| self.state(*link) == VoxelState::Free)
+            .filter(|link| {
+                self.face_neighbors(*link)
+                    .into_iter()
+                    .any(|successor| self.state(successor) == VoxelState::Unknown)
+            })
+            .collect::<HashSet<_>>();
+
+        let mut visited = HashSet::new();
+        let mut result = Vec::new();
+
+        for link in &frontier_links {
+            if !visited.insert(*link) {
+                continue;
+            }
+
+            let mut links = Vec::new();
+            let mut open = vec![*link];
+
+            while let Some(link) = open.pop() {
+                links.push(link);
+
+                for neighbor in self.face_neighbors(link) {
+                    if frontier_links.contains(&neighbor) && visited.insert(neighbor) {
+                        open.push(neighbor);
+                    }
+                }
+            }
+
+            links.sort_unstable();
+
+            let centroid = self.centroid(&links);
+            result.push(Frontier { links, centroid });
+        }
+
+        // The links are visited in the order of a hash set, so the clusters are sorted by their
+        // first link to be the same between runs.
+        result.sort_unstable_by_key(|frontier| frontier.links[0]);
+
+        result
+    }
+
+    /// Volume weighted center of the links in world space.
+    #[allow(clippy::cast_precision_loss)]
+    fn centroid(&self, links: &[SparseVoxelOctreeLink]) -> Vec3 {
+        let mut sum = Vec3::ZERO;
+        let mut volume = 0.0;
+
+        for link in links {
+            let link_volume = (self.link_size(*link) as f32).powi(3);
+
+            sum += self.node_position(*link) * link_volume;
+            volume += link_volume;
+        }
+
+        sum / volume
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3, Vec3};
+
+    use crate::{
+        OccupancyOctree, OccupancyParameters, OffMeshLink, SparseVoxelOctreeBuilder, VoxelState,
+        VoxelizedMesh,
+    };
+
+    #[test]
+    fn test_frontiers_after_scan() {
+        let mut octree = OccupancyOctree::new(
+            1.0,
+            Vec3::new(-16.0, -16.0, -16.0),
+            Vec3::new(16.0, 16.0, 16.0),
+            OccupancyParameters::default(),
+        );
+
+        assert!(octree.tree().frontiers().is_empty());
+
+        // Two scans in opposite directions that don't see each other.
+        octree.insert_scan(Vec3::new(0.5, 0.5, 0.5), &[Vec3::new(8.5, 0.5, 0.5)], -1.0);
+        octree.insert_scan(
+            Vec3::new(-4.5, 8.5, 0.5),
+            &[Vec3::new(-12.5, 8.5, 0.5)],
+            -1.0,
+        );
+
+        let mut frontiers = octree.tree().frontiers();
+        frontiers.sort_by(|a, b| a.centroid.x.total_cmp(&b.centroid.x));
+
+        assert_eq!(frontiers.len(), 2);
+        assert!(frontiers[0].centroid.x < -4.0 && frontiers[0].centroid.y > 8.0);
+        assert!(frontiers[1].centroid.x > 0.0 && frontiers[1].centroid.y < 1.0);
+
+        for frontier in &frontiers {
+            for link in &frontier.links {
+                assert_eq!(octree.tree().state(*link), VoxelState::Free);
+            }
+        }
+    }
+
+    #[test]
+    fn test_off_mesh_links_keep_frontiers_apart() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+
+        builder.set_unknown_by_default(true);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        builder.add_mesh_with_state(
+            VoxelizedMesh::new(
+                vec![
+                    UVec3::new(0, 0, 0),
+                    UVec3::new(10, 0, 0),
+                    UVec3::new(5, 10, 0),
+                ],
+                1.0,
+                IVec3::new(-8, -8, -8),
+            ),
+            VoxelState::Free,
+        );
+
+        let mut tree = builder.build();
+
+        tree.add_off_mesh_link(OffMeshLink {
+            start: Vec3::new(-7.5, -7.5, -7.5),
+            end: Vec3::new(2.5, -7.5, -7.5),
+            bidirectional: true,
+            cost: 1.0,
+            user_id: 0,
+        });
+
+        let frontiers = tree.frontiers();
+
+        assert_eq!(frontiers.len(), 3);
+        assert_eq!(tree.frontiers(), frontiers);
+
+        for pair in frontiers.windows(2) {
+            assert!(pair[0].links[0] < pair[1].links[0]);
+        }
+    }
+}
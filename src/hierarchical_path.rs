@@ -0,0 +1,446 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+use crate::{
+    path_finding::{reconstruct_path, OpenNode},
+    Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink, VoxelState,
+};
+
+/// Graph of the nodes of a coarse layer connected by portals, used to plan long paths in two
+/// phases.
+///
+/// The octree is split into regions, which are the nodes of the chosen layer and the nodes
+/// without children above it. Two regions are connected by a portal when a cell of one of them
+/// that isn't occupied has an edge to a cell of the other one with the options of the graph, so
+/// partially occupied regions are passable while blockers and filtered areas are respected. A
+/// path is first planned between the regions and then refined on the cells of the regions it
+/// crosses.
+///
+/// The graph doesn't borrow the octree, changes of the octree have to be passed to
+/// [`HierarchicalGraph::update`].
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{HierarchicalGraph, PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+/// use bevy_math::{IVec3, Vec3};
+///
+/// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+///
+/// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+/// builder.set_bounds(Vec3::new(-16.0, -16.0, -16.0), Vec3::new(16.0, 16.0, 16.0));
+///
+/// let octree = builder.build();
+///
+/// let graph = HierarchicalGraph::new(&octree, 1, PathOptions::default());
+///
+/// let start = octree.find_node(Vec3::new(-12.5, 0.5, 0.5)).unwrap();
+/// let goal = octree.find_node(Vec3::new(12.5, 0.5, 0.5)).unwrap();
+///
+/// let path = graph.find_path(&octree, start, goal).unwrap();
+///
+/// assert_eq!(path.links.first(), Some(&start));
+/// assert_eq!(path.links.last(), Some(&goal));
+/// ```
+#[derive(Debug, Clone)]
+pub struct HierarchicalGraph {
+    layer: usize,
+    options: PathOptions,
+    /// Regions reachable from a region together with the distance between their centers, scaled
+    /// by the lowest cost multiplier of the edges between them.
+    portals: HashMap<SparseVoxelOctreeLink, Vec<(SparseVoxelOctreeLink, f32)>>,
+}
+
+impl HierarchicalGraph {
+    /// Precomputes the portals between the regions of a layer using the options of the paths
+    /// that will be searched. Layers above the root are clamped to the root.
+    #[must_use]
+    pub fn new(octree: &SparseVoxelOctree, layer: usize, options: PathOptions) -> Self {
+        let mut graph = Self {
+            layer: layer.min(octree.layers.len() - 1),
+            options,
+            portals: HashMap::new(),
+        };
+
+        let mut regions = Vec::new();
+        let mut open = vec![SparseVoxelOctreeLink::new(octree.layers.len() - 1, 0, None)];
+
+        while let Some(link) = open.pop() {
+            let node = &octree.layers[link.layer_index][link.node_index];
+
+            match node.first_child {
+                Some(first_child) if link.layer_index > graph.layer => {
+                    for child in 0..8 {
+                        open.push(SparseVoxelOctreeLink::new(
+                            first_child.layer_index,
+                            first_child.node_index + child,
+                            None,
+                        ));
+                    }
+                }
+                _ => regions.push(link),
+            }
+        }
+
+        for region in regions {
+            graph.connect(octree, region);
+        }
+
+        graph
+    }
+
+    /// Layer the regions of the graph belong to.
+    #[must_use]
+    pub fn layer(&self) -> usize {
+        self.layer
+    }
+
+    /// Number of regions with at least one portal.
+    #[must_use]
+    pub fn region_count(&self) -> usize {
+        self.portals.len()
+    }
+
+    /// Recomputes the portals around links that changed, such as the links returned by
+    /// [`SparseVoxelOctree::set_voxel`] or [`crate::BlockerChange::links`].
+    pub fn update(&mut self, octree: &SparseVoxelOctree, changed: &[SparseVoxelOctreeLink]) {
+        let mut affected = HashSet::new();
+
+        for link in changed {
+            affected.insert(self.region(octree, *link));
+
+            for cell in octree.cells_within(*link) {
+                affected.insert(self.region(octree, cell));
+            }
+        }
+
+        // Portals leading into the affected regions are recomputed as well. The stored portals
+        // can't be used to find them, a region that was fully occupied has none.
+        let mut neighbors = affected
+            .iter()
+            .flat_map(|region| self.adjacent_regions(octree, *region))
+            .collect::<Vec<_>>();
+
+        for (start, end) in octree.off_mesh_links.ends() {
+            if affected.contains(&self.region(octree, end)) {
+                neighbors.push(self.region(octree, start));
+            }
+
+            if affected.contains(&self.region(octree, start)) {
+                neighbors.push(self.region(octree, end));
+            }
+        }
+
+        affected.extend(neighbors);
+
+        for region in affected {
+            self.portals.remove(&region);
+
+            if self.is_region(octree, region) {
+                self.connect(octree, region);
+            }
+        }
+    }
+
+    /// Finds a path between two links by planning between the regions first and then searching
+    /// only in the cells of the regions on the coarse path.
+    ///
+    /// The path is not guaranteed to be the cheapest one. If the regions on the coarse path
+    /// don't connect the links, the whole octree is searched instead, which is slower than
+    /// [`SparseVoxelOctree::find_path`] alone. This can happen on every query when the regions
+    /// are too coarse for the obstacles, for example in narrow corridors crossing a region
+    /// whose cells aren't connected to each other, so a finer layer should be used in that
+    /// case. Returns `None` without
+    /// searching the cells if no coarse path exists, unless partial paths are allowed by the
+    /// options of the graph.
+    #[must_use]
+    pub fn find_path(
+        &self,
+        octree: &SparseVoxelOctree,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+    ) -> Option<Path> {
+        let Some(corridor) = self.find_corridor(octree, start, goal) else {
+            // Portals exist wherever an edge between regions does, so the goal is unreachable.
+            return if self.options.allow_partial {
+                octree.find_path(start, goal, &self.options)
+            } else {
+                None
+            };
+        };
+
+        let corridor = corridor.into_iter().collect::<HashSet<_>>();
+        let path = octree.find_path_within(start, goal, &self.options, |link| {
+            corridor.contains(&self.region(octree, link))
+        });
+
+        if path.as_ref().is_some_and(|path| !path.is_partial) {
+            return path;
+        }
+
+        octree.find_path(start, goal, &self.options)
+    }
+
+    /// Finds the regions a path between two links crosses using A* over the portals.
+    fn find_corridor(
+        &self,
+        octree: &SparseVoxelOctree,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+    ) -> Option<Vec<SparseVoxelOctreeLink>> {
+        let start = self.region(octree, start);
+        let goal = self.region(octree, goal);
+
+        let mut open = BinaryHeap::new();
+        let mut costs = HashMap::new();
+        let mut parents = HashMap::new();
+
+        costs.insert(start, 0.0);
+        open.push(OpenNode {
+            estimate: octree.heuristic(start, goal),
+            cost: 0.0,
+            link: start,
+        });
+
+        while let Some(OpenNode { cost, link, .. }) = open.pop() {
+            if link == goal {
+                return Some(reconstruct_path(&parents, goal));
+            }
+
+            if costs.get(&link).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            for (neighbor, distance) in self.portals.get(&link).into_iter().flatten() {
+                let neighbor_cost = cost + distance;
+
+                if costs
+                    .get(neighbor)
+                    .is_some_and(|best| neighbor_cost >= *best)
+                {
+                    continue;
+                }
+
+                costs.insert(*neighbor, neighbor_cost);
+                parents.insert(*neighbor, link);
+                open.push(OpenNode {
+                    estimate: neighbor_cost + octree.heuristic(*neighbor, goal),
+                    cost: neighbor_cost,
+                    link: *neighbor,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Computes the portals leading out of a region.
+    fn connect(&mut self, octree: &SparseVoxelOctree, region: SparseVoxelOctreeLink) {
+        // Lowest cost of an edge into each neighbor relative to its length.
+        let mut neighbors = HashMap::new();
+
+        for cell in octree.cells_within(region) {
+            if octree.state(cell) == VoxelState::Occupied {
+                continue;
+            }
+
+            let position = octree.node_position(cell);
+
+            for successor in octree.successors(cell) {
+                let neighbor = self.region(octree, successor);
+
+                if neighbor == region {
+                    continue;
+                }
+
+                let Some(cost) = octree.edge_cost(cell, successor, &self.options) else {
+                    continue;
+                };
+
+                let distance = position.distance(octree.node_position(successor));
+                let multiplier = if distance > 0.0 { cost / distance } else { 1.0 };
+
+                neighbors
+                    .entry(neighbor)
+                    .and_modify(|lowest: &mut f32| *lowest = lowest.min(multiplier))
+                    .or_insert(multiplier);
+            }
+        }
+
+        if neighbors.is_empty() {
+            return;
+        }
+
+        let center = octree.node_position(region);
+
+        self.portals.insert(
+            region,
+            neighbors
+                .into_iter()
+                .map(|(neighbor, multiplier)| {
+                    let distance = center.distance(octree.node_position(neighbor));
+
+                    (neighbor, distance * multiplier)
+                })
+                .collect(),
+        );
+    }
+
+    /// Regions sharing a face with a region.
+    fn adjacent_regions(
+        &self,
+        octree: &SparseVoxelOctree,
+        region: SparseVoxelOctreeLink,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        octree.layers[region.layer_index][region.node_index]
+            .neighbors
+            .iter()
+            .flatten()
+            .flat_map(|neighbor| octree.cells_within(*neighbor))
+            .map(|cell| self.region(octree, cell))
+            .collect()
+    }
+
+    /// Region containing a link.
+    fn region(
+        &self,
+        octree: &SparseVoxelOctree,
+        link: SparseVoxelOctreeLink,
+    ) -> SparseVoxelOctreeLink {
+        let mut current = SparseVoxelOctreeLink::new(link.layer_index, link.node_index, None);
+
+        while current.layer_index < self.layer {
+            match octree.layers[current.layer_index][current.node_index].parent {
+                Some(parent) => current = parent,
+                None => break,
+            }
+        }
+
+        current
+    }
+
+    /// Returns true if a link is still a region of the graph after the octree changed.
+    fn is_region(&self, octree: &SparseVoxelOctree, link: SparseVoxelOctreeLink) -> bool {
+        link.layer_index == self.layer
+            || (link.layer_index > self.layer
+                && octree.layers[link.layer_index][link.node_index]
+                    .first_child
+                    .is_none())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3, Vec3};
+
+    use crate::{SparseVoxelOctreeBuilder, VolumeShape, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_hierarchical_path_around_wall() {
+        let mut voxels = Vec::new();
+
+        // Wall with a single 1x1 hole.
+        for y in 0..32 {
+            for z in 0..32 {
+                if y != 20 || z != 20 {
+                    voxels.push(UVec3::new(0, y, z));
+                }
+            }
+        }
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::new(0, -16, -16)));
+        builder.set_bounds(Vec3::new(-16.0, -16.0, -16.0), Vec3::new(16.0, 16.0, 16.0));
+
+        let mut tree = builder.build();
+        let mut graph = HierarchicalGraph::new(&tree, 2, PathOptions::default());
+
+        assert_eq!(graph.layer(), 2);
+        assert!(graph.region_count() > 0);
+
+        let start = tree.find_node(Vec3::new(-12.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(12.5, 0.5, 0.5)).unwrap();
+
+        let path = graph.find_path(&tree, start, goal).unwrap();
+        let optimal = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert_eq!(path.links.first(), Some(&start));
+        assert_eq!(path.links.last(), Some(&goal));
+        assert!(path.cost >= optimal.cost - 1e-4);
+
+        let hole = tree.find_node(Vec3::new(0.5, 4.5, 4.5)).unwrap();
+        assert!(path.links.contains(&hole));
+
+        for pair in path.links.windows(2) {
+            assert!(tree.successors(pair[0]).contains(&pair[1]));
+        }
+
+        // A blocker in the hole removes the portals through the wall.
+        let (min, max) = tree.link_bounds(hole);
+        let door = tree.add_blocker(VolumeShape::Box { min, max }, false);
+        let change = tree.set_blocker_enabled(door, true).unwrap();
+        graph.update(&tree, &change.links);
+
+        assert!(graph.find_corridor(&tree, start, goal).is_none());
+        assert!(graph.find_path(&tree, start, goal).is_none());
+
+        // Partial paths still search the cells.
+        let partial = HierarchicalGraph::new(
+            &tree,
+            2,
+            PathOptions {
+                allow_partial: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(partial.find_path(&tree, start, goal).unwrap().is_partial);
+
+        let change = tree.set_blocker_enabled(door, false).unwrap();
+        graph.update(&tree, &change.links);
+
+        assert!(graph.find_path(&tree, start, goal).is_some());
+
+        // Closing the hole removes the portals through the wall.
+        let changed = tree.set_voxel(Vec3::new(0.5, 4.5, 4.5), true).unwrap();
+        graph.update(&tree, &changed);
+
+        assert!(graph.find_corridor(&tree, start, goal).is_none());
+        assert!(graph.find_path(&tree, start, goal).is_none());
+    }
+
+    #[test]
+    fn test_update_connects_cleared_region() {
+        let mut voxels = Vec::new();
+
+        // Solid wall, so the regions inside of it have no portals.
+        for x in 0..4 {
+            for y in 0..16 {
+                for z in 0..16 {
+                    voxels.push(UVec3::new(x, y, z));
+                }
+            }
+        }
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::new(0, -8, -8)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let mut tree = builder.build();
+        let mut graph = HierarchicalGraph::new(&tree, 0, PathOptions::default());
+
+        let changed = tree.set_voxel(Vec3::new(0.5, 0.5, 0.5), false).unwrap();
+        graph.update(&tree, &changed);
+
+        let start = tree.find_node(Vec3::new(-4.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        assert!(graph.find_corridor(&tree, start, goal).is_some());
+
+        let path = graph.find_path(&tree, start, goal).unwrap();
+        assert_eq!(path.links.last(), Some(&goal));
+    }
+}
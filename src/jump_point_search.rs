@@ -0,0 +1,393 @@
+// Resource: https://users.cecs.anu.edu.au/~dharabor/data/papers/harabor-grastien-aaai11.pdf
+// Resource: https://webdocs.cs.ualberta.ca/~nathanst/papers/canonicalAstar.pdf
+
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::{
+    compound_node::CompoundNode,
+    consts::{OPPOSITE_FACES, SUBNODE_NEIGHBORS},
+    path_finding::{reconstruct_path, OpenNode},
+    Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink, UnknownSpace,
+};
+
+/// Order of the axes of the faces in canonical paths, moves along x come first, then y and z.
+///
+/// Uses the same face indexing as `NEIGHBOR_POSITION_OFFSETS`.
+const FACE_AXIS_RANKS: [u8; 6] = [0, 2, 0, 2, 1, 1];
+
+/// Rank of the last axis of canonical paths, moves along it have no natural turns.
+const LAST_AXIS_RANK: u8 = 2;
+
+/// Bit of a mask of entered faces allowing all moves, used for links entered from another leaf,
+/// a larger node or an off-mesh link.
+const ANY_FACE: u8 = 1 << 6;
+
+/// Best known cost of a link together with the faces it was entered through for that cost.
+#[derive(Debug, Clone, Copy)]
+struct JumpEntry {
+    cost: f32,
+    /// Bits of the faces through which the link was entered from another subnode of the same
+    /// leaf, or [`ANY_FACE`].
+    faces: u8,
+    /// Faces whose moves were already expanded.
+    expanded: u8,
+}
+
+impl SparseVoxelOctree {
+    /// Finds the cheapest path between two links using A* that breaks the symmetry of paths
+    /// inside of partially filled leaves.
+    ///
+    /// Inside of a leaf all moves between subnodes have the same cost, so there are many
+    /// equally cheap paths between two subnodes. The search only follows the canonical one,
+    /// which moves along x first, then along y and z, and turns back to an earlier axis only
+    /// when the occupancy mask of the leaf shows that an obstacle prevented the move before.
+    /// Runs of free subnodes along the last axis are jumped over without adding them to the
+    /// open set. Moves between leaves, into larger air nodes and along off-mesh links are never
+    /// pruned, neither are moves in leaves with different areas, unknown voxels with a cost or
+    /// cost volumes.
+    ///
+    /// The costs are the same as in [`SparseVoxelOctree::find_path`].
+    /// [`PathOptions::allow_partial`] is ignored.
+    ///
+    /// The search is opt-in because it is slower than [`SparseVoxelOctree::find_path`] on
+    /// cluttered maps. In a 32x32x32 octree with 1-30% of randomly occupied voxels almost every
+    /// subnode becomes a jump point and scanning the runs costs more than the expansions it
+    /// saves, so it took 1.3-1.7x longer. In open space the octree already merges free voxels into
+    /// large nodes and both searches expand about the same number of links. The `Cluttered path`
+    /// group of the benches compares both searches.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let options = PathOptions::default();
+    /// let path = octree.find_path_jps(start, goal, &options).unwrap();
+    ///
+    /// assert_eq!(path.links.last(), Some(&goal));
+    /// assert!((path.cost - octree.find_path(start, goal, &options).unwrap().cost).abs() < 1e-3);
+    /// ```
+    #[must_use]
+    pub fn find_path_jps(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: &PathOptions,
+    ) -> Option<Path> {
+        let mut open = BinaryHeap::new();
+        let mut entries = HashMap::<SparseVoxelOctreeLink, JumpEntry>::new();
+        let mut parents = HashMap::new();
+
+        entries.insert(
+            start,
+            JumpEntry {
+                cost: 0.0,
+                faces: ANY_FACE,
+                expanded: 0,
+            },
+        );
+        open.push(OpenNode {
+            estimate: self.heuristic(start, goal),
+            cost: 0.0,
+            link: start,
+        });
+
+        while let Some(OpenNode { cost, link, .. }) = open.pop() {
+            if link == goal {
+                return Some(self.path(reconstruct_path(&parents, link), cost));
+            }
+
+            let Some(entry) = entries.get_mut(&link) else {
+                continue;
+            };
+
+            // Stale entries and links already expanded for all faces they were entered through.
+            if cost > entry.cost || entry.faces & !entry.expanded == 0 {
+                continue;
+            }
+
+            let faces = entry.faces;
+            entry.expanded = faces;
+
+            for (mut next, face, edge_cost) in self.canonical_successors(link, faces, options) {
+                let mut previous = link;
+                let mut next_cost = cost + edge_cost;
+
+                loop {
+                    let bit = face.map_or(ANY_FACE, |face| 1 << face);
+
+                    let faces = match entries.get_mut(&next) {
+                        Some(entry) if next_cost > entry.cost => break,
+                        // Equally cheap paths entering through other faces allow their moves too.
+                        Some(entry) if next_cost >= entry.cost => {
+                            if entry.faces & bit != 0 {
+                                break;
+                            }
+
+                            entry.faces |= bit;
+                            entry.faces
+                        }
+                        _ => {
+                            entries.insert(
+                                next,
+                                JumpEntry {
+                                    cost: next_cost,
+                                    faces: bit,
+                                    expanded: 0,
+                                },
+                            );
+                            parents.insert(next, previous);
+
+                            bit
+                        }
+                    };
+
+                    // Subnodes whose only canonical move continues in the same direction are
+                    // passed through. Subnodes on the border of the leaf always have moves into
+                    // the neighboring nodes.
+                    let is_run = next != goal
+                        && face.is_some_and(|face| {
+                            faces == 1 << face && FACE_AXIS_RANKS[face] == LAST_AXIS_RANK
+                        })
+                        && next.subnode_index.is_some_and(|subnode| {
+                            (0..6).all(|face| !CompoundNode::is_face(subnode, face))
+                        });
+
+                    if is_run {
+                        if let [(forward, forward_face, forward_cost)] =
+                            self.canonical_successors(next, faces, options)[..]
+                        {
+                            if forward_face == face {
+                                if let Some(entry) = entries.get_mut(&next) {
+                                    entry.expanded = faces;
+                                }
+
+                                previous = next;
+                                next = forward;
+                                next_cost += forward_cost;
+                                continue;
+                            }
+                        }
+                    }
+
+                    open.push(OpenNode {
+                        estimate: next_cost + self.heuristic(next, goal),
+                        cost: next_cost,
+                        link: next,
+                    });
+
+                    break;
+                }
+            }
+        }
+
+        None
+    }
+
+    /// Successors of a link entered through `faces` that continue canonical paths, together
+    /// with the face of the leaf they are entered through and the cost of the edge.
+    fn canonical_successors(
+        &self,
+        link: SparseVoxelOctreeLink,
+        faces: u8,
+        options: &PathOptions,
+    ) -> Vec<(SparseVoxelOctreeLink, Option<usize>, f32)> {
+        // Faces of the leaf leading to each of its subnodes from the subnode of the link.
+        let mut leaf_faces = [None; 64];
+
+        if let Some(subnode) = link
+            .subnode_index
+            .filter(|_| self.has_uniform_costs(link.node_index, options))
+        {
+            for face in 0..6 {
+                if !CompoundNode::is_face(subnode, face) {
+                    leaf_faces[SUBNODE_NEIGHBORS[subnode as usize][face] as usize] = Some(face);
+                }
+            }
+        }
+
+        self.successors(link)
+            .into_iter()
+            .filter_map(|successor| {
+                let edge_cost = self.edge_cost(link, successor, options)?;
+
+                let face = successor
+                    .subnode_index
+                    .filter(|_| {
+                        successor.layer_index == link.layer_index
+                            && successor.node_index == link.node_index
+                            && self.off_mesh_link_between(link, successor).is_none()
+                    })
+                    .and_then(|next| leaf_faces[next as usize]);
+
+                if let Some(face) = face {
+                    let is_canonical = faces & ANY_FACE != 0
+                        || (0..6).any(|entered| {
+                            faces & (1 << entered) != 0
+                                && self.is_canonical_move(link, entered, face, options)
+                        });
+
+                    if !is_canonical {
+                        return None;
+                    }
+                }
+
+                Some((successor, face, edge_cost))
+            })
+            .collect()
+    }
+
+    /// Returns true if a move through `face` of a subnode entered through `entered` is part of
+    /// a canonical path.
+    fn is_canonical_move(
+        &self,
+        link: SparseVoxelOctreeLink,
+        entered: usize,
+        face: usize,
+        options: &PathOptions,
+    ) -> bool {
+        if face == entered || FACE_AXIS_RANKS[face] > FACE_AXIS_RANKS[entered] {
+            return true;
+        }
+
+        if face == OPPOSITE_FACES[entered] {
+            return false;
+        }
+
+        // A move along an earlier axis is only needed when it couldn't be made before the last
+        // move.
+        let Some(subnode) = link.subnode_index else {
+            return true;
+        };
+
+        let previous = SUBNODE_NEIGHBORS[subnode as usize][OPPOSITE_FACES[entered]];
+
+        CompoundNode::is_face(previous, face)
+            || !self.is_free_subnode(link, SUBNODE_NEIGHBORS[previous as usize][face], options)
+    }
+
+    /// Returns true if a subnode of the leaf of `link` can be entered.
+    fn is_free_subnode(
+        &self,
+        link: SparseVoxelOctreeLink,
+        subnode: u8,
+        options: &PathOptions,
+    ) -> bool {
+        let is_unknown_blocked = options.unknown_space == UnknownSpace::Blocked
+            && self.unknown[link.node_index].get_by_index(subnode);
+
+        !self.leafs[link.node_index].get_by_index(subnode)
+            && !is_unknown_blocked
+            && !self.is_blocked(SparseVoxelOctreeLink::new(
+                link.layer_index,
+                link.node_index,
+                Some(subnode),
+            ))
+    }
+
+    /// Returns true if all moves between the subnodes of a leaf have the same cost.
+    fn has_uniform_costs(&self, node_index: usize, options: &PathOptions) -> bool {
+        let has_unknown_cost = matches!(options.unknown_space, UnknownSpace::Cost(_))
+            && !self.unknown[node_index].is_empty();
+
+        self.areas[node_index].is_none()
+            && !has_unknown_cost
+            && self.cost_multiplier(SparseVoxelOctreeLink::new(0, node_index, None)) <= 1.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3, Vec3};
+
+    use crate::{OffMeshLink, SparseVoxelOctreeBuilder, VolumeShape, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_jump_point_search_matches_find_path() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::sphere(3.0, 1.0, IVec3::ZERO));
+
+        // Walls with holes crossing partially filled leaves.
+        let wall = (0..16)
+            .flat_map(|y| (0..16).map(move |z| UVec3::new(5, y, z)))
+            .filter(|voxel| (voxel.y * 7 + voxel.z * 3) % 5 != 0)
+            .collect::<Vec<_>>();
+
+        builder.add_mesh(VoxelizedMesh::new(wall, 1.0, IVec3::new(-8, -8, -8)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        builder.add_blocker(
+            VolumeShape::Box {
+                min: Vec3::new(-4.0, 4.0, -4.0),
+                max: Vec3::new(-2.0, 6.0, 4.0),
+            },
+            true,
+        );
+
+        let mut tree = builder.build();
+
+        tree.add_off_mesh_link(OffMeshLink {
+            start: Vec3::new(-7.5, -7.5, -7.5),
+            end: Vec3::new(7.5, -7.5, 7.5),
+            bidirectional: false,
+            cost: 30.0,
+            user_id: 0,
+        });
+        tree.add_cost_volume(
+            VolumeShape::Sphere {
+                center: Vec3::new(0.0, -4.0, 0.0),
+                radius: 2.0,
+            },
+            3.0,
+        );
+
+        let options = PathOptions::default();
+        let points = [
+            Vec3::new(-7.5, -7.5, -7.5),
+            Vec3::new(7.5, 7.5, 7.5),
+            Vec3::new(-6.5, 0.5, 0.5),
+            Vec3::new(6.5, 0.5, 0.5),
+            Vec3::new(3.5, 3.5, -3.5),
+            Vec3::new(-3.5, -3.5, 3.5),
+            Vec3::new(-2.5, 2.5, 2.5),
+            Vec3::new(-3.0, 6.5, 0.0),
+            Vec3::new(7.5, -7.5, 7.5),
+        ];
+
+        for from in points {
+            for to in points {
+                let start = tree.find_node(from).unwrap();
+                let goal = tree.find_node(to).unwrap();
+
+                let expected = tree.find_path(start, goal, &options);
+                let path = tree.find_path_jps(start, goal, &options);
+
+                assert_eq!(path.is_some(), expected.is_some(), "{from} -> {to}");
+
+                let (Some(path), Some(expected)) = (path, expected) else {
+                    continue;
+                };
+
+                assert!((path.cost - expected.cost).abs() < 1e-3, "{from} -> {to}");
+                assert_eq!(path.links.first(), Some(&start));
+                assert_eq!(path.links.last(), Some(&goal));
+
+                for pair in path.links.windows(2) {
+                    assert!(tree.successors(pair[0]).contains(&pair[1]));
+                }
+            }
+        }
+    }
+}
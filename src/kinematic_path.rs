@@ -0,0 +1,437 @@
+// Resource: https://ai.stanford.edu/~ddolgov/papers/dolgov_gpp_stair08.pdf
+
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    f32::consts::{FRAC_1_SQRT_2, TAU},
+};
+
+use bevy_math::{IVec3, Vec3};
+
+use crate::{DistanceField, PathOptions, SparseVoxelOctree};
+
+/// Number of collision checks along a single motion primitive.
+const SUBSTEPS: u32 = 4;
+
+/// Position and orientation of a vehicle that can't turn in place.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KinematicState {
+    /// Position in world space.
+    pub position: Vec3,
+    /// Rotation around the y axis in radians. Heading of 0 points along the x axis and
+    /// positive headings turn towards the z axis.
+    pub heading: f32,
+    /// Angle above the horizontal plane in radians.
+    pub pitch: f32,
+}
+
+impl KinematicState {
+    /// Unit vector the state moves along.
+    #[must_use]
+    pub fn direction(&self) -> Vec3 {
+        let (sin_heading, cos_heading) = self.heading.sin_cos();
+        let (sin_pitch, cos_pitch) = self.pitch.sin_cos();
+
+        Vec3::new(cos_heading * cos_pitch, sin_pitch, sin_heading * cos_pitch)
+    }
+}
+
+/// Options of [`SparseVoxelOctree::find_kinematic_path`].
+#[derive(Debug, Clone)]
+pub struct KinematicOptions {
+    /// Minimal radius of a turn in world units, both horizontally and vertically.
+    pub min_turn_radius: f32,
+    /// Maximal angle of climbing or descending in radians.
+    pub max_climb_angle: f32,
+    /// Length of a single motion primitive in world units. Shorter steps find paths through
+    /// narrower spaces, but expand more states.
+    pub step_length: f32,
+    /// Distance from the goal at which it's considered reached.
+    pub goal_tolerance: f32,
+    /// Maximal number of expanded states before the search gives up.
+    pub max_expansions: usize,
+    /// Options of the distance field used as the heuristic. Only links reachable under these
+    /// options are entered.
+    pub path: PathOptions,
+}
+
+impl Default for KinematicOptions {
+    fn default() -> Self {
+        Self {
+            min_turn_radius: 4.0,
+            max_climb_angle: 20f32.to_radians(),
+            step_length: 1.0,
+            goal_tolerance: 1.0,
+            max_expansions: 50_000,
+            path: PathOptions::default(),
+        }
+    }
+}
+
+/// Path found by [`SparseVoxelOctree::find_kinematic_path`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct KinematicPath {
+    /// States from the start to the state within the tolerance of the goal, one motion
+    /// primitive apart.
+    pub states: Vec<KinematicState>,
+    /// Length of the path in world units.
+    pub length: f32,
+}
+
+impl SparseVoxelOctree {
+    /// Finds a path that respects a minimal turn radius and a maximal climb angle using
+    /// hybrid A*.
+    ///
+    /// The search expands continuous states with motion primitives that turn left, right or
+    /// not at all, combined with pitching up, down or not at all, and keeps the cheapest state
+    /// in every cell of a grid over position, heading and pitch. Primitives are checked for
+    /// collisions with [`SparseVoxelOctree::is_in_line_of_sight`] and the distance from a
+    /// [`SparseVoxelOctree::distance_field`] of the goal guides the search around obstacles.
+    ///
+    /// Returns `None` if the start or the goal are outside of the octree or no path was found
+    /// within `max_expansions`. The pitch of the start is clamped to the climb angle.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{KinematicOptions, KinematicState, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let start = KinematicState {
+    ///     position: Vec3::new(-6.5, 0.5, 0.5),
+    ///     heading: 0.0,
+    ///     pitch: 0.0,
+    /// };
+    /// let goal = Vec3::new(6.5, 2.5, 0.5);
+    ///
+    /// let path = octree
+    ///     .find_kinematic_path(start, goal, &KinematicOptions::default())
+    ///     .unwrap();
+    ///
+    /// assert!(path.states.last().unwrap().position.distance(goal) <= 1.0);
+    /// ```
+    #[must_use]
+    pub fn find_kinematic_path(
+        &self,
+        start: KinematicState,
+        goal: Vec3,
+        options: &KinematicOptions,
+    ) -> Option<KinematicPath> {
+        let goal_link = self.find_node(goal)?;
+        let distances = self.distance_field(&[goal_link], f32::INFINITY, &options.path);
+
+        self.find_kinematic_path_with(start, goal, options, &distances)
+    }
+
+    /// Same as [`SparseVoxelOctree::find_kinematic_path`], but uses a distance field computed
+    /// by the caller, so it can be reused by all searches towards the same goal.
+    ///
+    /// The field has to be computed from the node containing the goal with the path options of
+    /// `options`, otherwise the found paths may enter links that can't be entered and the search
+    /// may expand more states. It has to be recomputed after the octree changes.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{KinematicOptions, KinematicState, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    /// let options = KinematicOptions::default();
+    ///
+    /// let goal = Vec3::new(6.5, 2.5, 0.5);
+    /// let goal_link = octree.find_node(goal).unwrap();
+    /// let distances = octree.distance_field(&[goal_link], f32::INFINITY, &options.path);
+    ///
+    /// for z in [-2.5, 0.5, 2.5] {
+    ///     let start = KinematicState {
+    ///         position: Vec3::new(-6.5, 0.5, z),
+    ///         heading: 0.0,
+    ///         pitch: 0.0,
+    ///     };
+    ///
+    ///     let path = octree
+    ///         .find_kinematic_path_with(start, goal, &options, &distances)
+    ///         .unwrap();
+    ///
+    ///     assert!(path.states.last().unwrap().position.distance(goal) <= 1.0);
+    /// }
+    /// ```
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn find_kinematic_path_with(
+        &self,
+        start: KinematicState,
+        goal: Vec3,
+        options: &KinematicOptions,
+        distances: &DistanceField,
+    ) -> Option<KinematicPath> {
+        let start = KinematicState {
+            pitch: start
+                .pitch
+                .clamp(-options.max_climb_angle, options.max_climb_angle),
+            ..start
+        };
+
+        // Angle of a primitive along the smallest allowed turn.
+        let turn = options.step_length / options.min_turn_radius.max(f32::EPSILON);
+        let heading_bins = ((TAU / turn).round() as i32).max(1);
+
+        let cell = |state: &KinematicState| {
+            (
+                (state.position / options.step_length).floor().as_ivec3(),
+                ((state.heading / turn).round() as i32).rem_euclid(heading_bins),
+                (state.pitch / turn).round() as i32,
+            )
+        };
+
+        let mut states = vec![start];
+        let mut parents = vec![None];
+        let mut costs: HashMap<(IVec3, i32, i32), f32> = HashMap::new();
+        let mut open = BinaryHeap::new();
+
+        costs.insert(cell(&start), 0.0);
+        open.push(KinematicNode {
+            estimate: self.kinematic_heuristic(&start, goal, distances)?,
+            cost: 0.0,
+            index: 0,
+        });
+
+        let mut expansions = 0;
+
+        while let Some(KinematicNode { cost, index, .. }) = open.pop() {
+            let state = states[index];
+
+            if costs.get(&cell(&state)).is_some_and(|best| cost > *best) {
+                continue;
+            }
+
+            if state.position.distance(goal) <= options.goal_tolerance
+                && self.is_in_line_of_sight(state.position, goal)
+            {
+                let mut path = vec![state];
+                let mut current = index;
+
+                while let Some(parent) = parents[current] {
+                    path.push(states[parent]);
+                    current = parent;
+                }
+
+                path.reverse();
+
+                return Some(KinematicPath {
+                    states: path,
+                    length: cost,
+                });
+            }
+
+            if expansions >= options.max_expansions {
+                return None;
+            }
+
+            expansions += 1;
+
+            for (yaw, climb) in primitives(turn) {
+                let climb = (state.pitch + climb)
+                    .clamp(-options.max_climb_angle, options.max_climb_angle)
+                    - state.pitch;
+
+                let Some(next) = self.motion_primitive(state, yaw, climb, options, distances)
+                else {
+                    continue;
+                };
+
+                let Some(distance) = self.kinematic_heuristic(&next, goal, distances) else {
+                    continue;
+                };
+
+                let next_cost = cost + options.step_length;
+                let next_cell = cell(&next);
+
+                if costs.get(&next_cell).is_some_and(|best| next_cost >= *best) {
+                    continue;
+                }
+
+                costs.insert(next_cell, next_cost);
+                states.push(next);
+                parents.push(Some(index));
+                open.push(KinematicNode {
+                    estimate: next_cost + distance,
+                    cost: next_cost,
+                    index: states.len() - 1,
+                });
+            }
+        }
+
+        None
+    }
+
+    /// Moves a state along a primitive and checks it for collisions. Returns `None` if the
+    /// primitive enters a link that can't be entered or can't reach the goal.
+    #[allow(clippy::cast_precision_loss)]
+    fn motion_primitive(
+        &self,
+        from: KinematicState,
+        yaw: f32,
+        climb: f32,
+        options: &KinematicOptions,
+        distances: &DistanceField,
+    ) -> Option<KinematicState> {
+        let mut state = from;
+        let mut link = self.find_node(from.position)?;
+
+        for _ in 0..SUBSTEPS {
+            let previous = state.position;
+            let previous_link = link;
+
+            state.heading += yaw / SUBSTEPS as f32;
+            state.pitch += climb / SUBSTEPS as f32;
+            state.position += state.direction() * (options.step_length / SUBSTEPS as f32);
+
+            link = self.find_node(state.position)?;
+
+            if distances.distance(link).is_none()
+                || (link != previous_link
+                    && self.edge_cost(previous_link, link, &options.path).is_none())
+                || !self.is_in_line_of_sight(previous, state.position)
+            {
+                return None;
+            }
+        }
+
+        state.heading = state.heading.rem_euclid(TAU);
+
+        Some(state)
+    }
+
+    /// Estimate of the remaining length from the distance field, which accounts for
+    /// obstacles, but not for turning. Returns `None` if the goal is unreachable.
+    fn kinematic_heuristic(
+        &self,
+        state: &KinematicState,
+        goal: Vec3,
+        distances: &DistanceField,
+    ) -> Option<f32> {
+        let distance = distances.distance(self.find_node(state.position)?)?;
+
+        Some(distance.max(state.position.distance(goal)))
+    }
+}
+
+/// Changes of heading and pitch of all motion primitives. Primitives turning along both axes
+/// at once are scaled down, so they don't turn sharper than the turn radius allows.
+fn primitives(turn: f32) -> impl Iterator<Item = (f32, f32)> {
+    [-1.0, 0.0, 1.0].into_iter().flat_map(move |yaw: f32| {
+        [-1.0, 0.0, 1.0].into_iter().map(move |climb: f32| {
+            let scale = if yaw != 0.0 && climb != 0.0 {
+                FRAC_1_SQRT_2
+            } else {
+                1.0
+            };
+
+            (yaw * turn * scale, climb * turn * scale)
+        })
+    })
+}
+
+/// Entry of the open set of the hybrid A* search ordered by the lowest estimate first.
+#[derive(Debug, Clone, Copy)]
+struct KinematicNode {
+    estimate: f32,
+    cost: f32,
+    index: usize,
+}
+
+impl PartialEq for KinematicNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for KinematicNode {}
+
+impl PartialOrd for KinematicNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KinematicNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the lowest estimate first. Ties prefer longer paths.
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| self.cost.total_cmp(&other.cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SparseVoxelOctreeBuilder, VoxelState, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_kinematic_path_around_sphere() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::sphere(3.0, 1.0, IVec3::ZERO));
+        builder.set_bounds(Vec3::new(-16.0, -16.0, -16.0), Vec3::new(16.0, 16.0, 16.0));
+
+        let tree = builder.build();
+        let options = KinematicOptions::default();
+
+        // The goal is behind the start, so the path has to turn around.
+        let start = KinematicState {
+            position: Vec3::new(-6.5, 0.5, 0.5),
+            heading: TAU / 2.0,
+            pitch: 0.0,
+        };
+        let goal = Vec3::new(8.5, 0.5, 0.5);
+
+        let path = tree.find_kinematic_path(start, goal, &options).unwrap();
+
+        assert_eq!(path.states.first(), Some(&start));
+        assert!(path.states.last().unwrap().position.distance(goal) <= options.goal_tolerance);
+        assert!(path.length >= start.position.distance(goal));
+
+        let turn = options.step_length / options.min_turn_radius;
+
+        for pair in path.states.windows(2) {
+            let heading =
+                (pair[1].heading - pair[0].heading + TAU / 2.0).rem_euclid(TAU) - TAU / 2.0;
+
+            assert!(heading.abs() <= turn + 1e-4);
+            assert!((pair[1].pitch - pair[0].pitch).abs() <= turn + 1e-4);
+            assert!(pair[1].pitch.abs() <= options.max_climb_angle + 1e-4);
+            assert_ne!(
+                tree.voxel_state(pair[1].position),
+                Some(VoxelState::Occupied)
+            );
+        }
+
+        let limited = KinematicOptions {
+            max_expansions: 10,
+            ..Default::default()
+        };
+
+        assert!(tree.find_kinematic_path(start, goal, &limited).is_none());
+
+        // A distance field computed once finds the same path.
+        let goal_link = tree.find_node(goal).unwrap();
+        let distances = tree.distance_field(&[goal_link], f32::INFINITY, &options.path);
+        let reused = tree
+            .find_kinematic_path_with(start, goal, &options, &distances)
+            .unwrap();
+
+        assert_eq!(reused.states, path.states);
+    }
+}
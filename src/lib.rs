@@ -5,15 +5,42 @@
 
 #![warn(clippy::pedantic)]
 
+mod anytime_search;
+mod area_type;
+mod batch_path;
+mod bidirectional_search;
+mod blocker;
 mod cohen_sutherland;
 mod compound_node;
+mod conflict_based_search;
 mod consts;
+mod cooperative_path;
+mod cost_volume;
+mod d_star_lite;
+mod distance_field;
+mod flow_field;
+mod frontier;
+mod hierarchical_path;
+mod jump_point_search;
+mod kinematic_path;
 mod morton_code;
+mod occupancy_octree;
+mod octomap;
+mod off_mesh_link;
+mod path_cache;
+mod path_finding;
+mod path_repair;
+mod path_search;
 mod point;
+mod query_filter;
 mod sparse_voxel_octree;
 mod sparse_voxel_octree_builder;
 mod sparse_voxel_octree_link;
 mod sparse_voxel_octree_node;
+#[cfg(test)]
+mod test_fixtures;
+mod trajectory;
+mod voxel_state;
 mod voxelized_mesh;
 
 #[cfg(not(feature = "bevy"))]
@@ -24,11 +51,50 @@ mod bevy_vec {
 #[cfg(feature = "bevy")]
 mod bevy_vec {}
 
+pub use anytime_search::AnytimeSearch;
+pub use area_type::AreaType;
+pub use batch_path::PathRequest;
+pub use batch_path::PathResult;
 pub use bevy_vec::*;
+pub use blocker::BlockerChange;
+pub use blocker::BlockerId;
+pub use blocker::PathId;
+pub use conflict_based_search::ConflictBasedOptions;
+pub use cooperative_path::CooperativeAgent;
+pub use cooperative_path::CooperativeOptions;
+pub use cooperative_path::ReservationTable;
+pub use cost_volume::VolumeId;
+pub use cost_volume::VolumeShape;
+pub use d_star_lite::DStarLite;
+pub use distance_field::DistanceField;
+pub use flow_field::FlowField;
+pub use frontier::Frontier;
+pub use hierarchical_path::HierarchicalGraph;
+pub use kinematic_path::KinematicOptions;
+pub use kinematic_path::KinematicPath;
+pub use kinematic_path::KinematicState;
+pub use occupancy_octree::OccupancyOctree;
+pub use occupancy_octree::OccupancyParameters;
+pub use octomap::OctoMapError;
+pub use off_mesh_link::OffMeshLink;
+pub use off_mesh_link::OffMeshLinkId;
+pub use path_cache::PathCache;
+pub use path_cache::PathCacheStats;
+pub use path_finding::Path;
+pub use path_finding::PathOptions;
+pub use path_finding::UnknownSpace;
+pub use path_repair::PathValidity;
+pub use path_search::PathSearch;
+pub use path_search::PathSearchStatus;
 pub use point::DistanceSquared;
 pub use point::ManhattanDistance;
+pub use query_filter::QueryFilter;
 pub use sparse_voxel_octree::SparseVoxelOctree;
 pub use sparse_voxel_octree_builder::SparseVoxelOctreeBuilder;
 pub use sparse_voxel_octree_link::SparseVoxelOctreeLink;
+pub use trajectory::Trajectory;
+pub use trajectory::TrajectoryLimits;
+pub use trajectory::TrajectorySample;
+pub use voxel_state::VoxelState;
 pub use voxelized_mesh::VoxelizeError;
 pub use voxelized_mesh::VoxelizedMesh;
@@ -0,0 +1,715 @@
+// Resource: https://octomap.github.io/
+
+use std::collections::HashSet;
+
+use bevy_math::{IVec3, UVec3, Vec3};
+
+use crate::{
+    morton_code::MortonCode, SparseVoxelOctree, SparseVoxelOctreeBuilder, SparseVoxelOctreeLink,
+    VoxelState,
+};
+
+/// Parameters of the probabilistic sensor model used by [`OccupancyOctree`].
+///
+/// All values are probabilities in the range (0, 1). They are converted to log-odds internally.
+/// The defaults are the ones used by `OctoMap`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OccupancyParameters {
+    /// Probability that a voxel is occupied when a sensor reports a hit in it.
+    pub hit_probability: f32,
+    /// Probability that a voxel is occupied when a sensor ray passes through it.
+    pub miss_probability: f32,
+    /// Lower clamping bound of the occupancy probability of a voxel.
+    pub clamping_min: f32,
+    /// Upper clamping bound of the occupancy probability of a voxel.
+    pub clamping_max: f32,
+    /// Voxels with an occupancy probability at or above this threshold are treated as blocked.
+    pub occupancy_threshold: f32,
+}
+
+impl Default for OccupancyParameters {
+    fn default() -> Self {
+        Self {
+            hit_probability: 0.7,
+            miss_probability: 0.4,
+            clamping_min: 0.1192,
+            clamping_max: 0.971,
+            occupancy_threshold: 0.5,
+        }
+    }
+}
+
+/// Sparse voxel octree with a probabilistic occupancy value for each voxel.
+///
+/// Each observed voxel stores a clamped log-odds value that is updated by hit and miss
+/// observations. Whenever the value of a voxel crosses the occupancy threshold, the voxel is
+/// filled or emptied in the underlying [`SparseVoxelOctree`], so `successors` and
+/// `is_in_line_of_sight` of [`OccupancyOctree::tree`] always reflect the thresholded map.
+/// Voxels that were never observed are [`VoxelState::Unknown`] in the underlying tree.
+///
+/// The log-odds values are kept in their own octree where children holding the same value are
+/// pruned into their parent. Since values saturate at the clamping bounds, large areas that were
+/// observed many times collapse into single nodes and memory stays bounded.
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{OccupancyOctree, OccupancyParameters};
+/// use bevy_math::Vec3;
+///
+/// let mut octree = OccupancyOctree::new(
+///     1.0,
+///     Vec3::new(-8.0, -8.0, -8.0),
+///     Vec3::new(8.0, 8.0, 8.0),
+///     OccupancyParameters::default(),
+/// );
+///
+/// octree.update_voxel(Vec3::new(0.5, 0.5, 0.5), true);
+///
+/// assert!(octree.is_occupied(Vec3::new(0.5, 0.5, 0.5)));
+/// ```
+pub struct OccupancyOctree {
+    tree: SparseVoxelOctree,
+    log_odds: LogOddsNode,
+    hit: f32,
+    miss: f32,
+    clamping_min: f32,
+    clamping_max: f32,
+    occupancy_threshold: f32,
+}
+
+impl OccupancyOctree {
+    /// Creates an occupancy octree covering the bounds specified in world space.
+    ///
+    /// The bounds are extended the same way as in [`SparseVoxelOctreeBuilder::set_bounds`].
+    #[must_use]
+    pub fn new(voxel_size: f32, min: Vec3, max: Vec3, parameters: OccupancyParameters) -> Self {
+        // The octree needs at least two layers to have a root node above the leaf nodes.
+        let max = max.max(min + Vec3::splat(8.0 * voxel_size));
+
+        let mut builder = SparseVoxelOctreeBuilder::new(voxel_size);
+        builder.set_bounds(min, max);
+        builder.set_unknown_by_default(true);
+
+        Self::from_tree(builder.build(), parameters)
+    }
+
+    /// Creates an occupancy octree on top of an existing sparse voxel octree.
+    ///
+    /// Voxels that are filled in the tree start with the upper clamping bound, all other
+    /// voxels start unobserved.
+    #[must_use]
+    pub fn from_tree(tree: SparseVoxelOctree, parameters: OccupancyParameters) -> Self {
+        let mut octree = Self {
+            tree,
+            log_odds: LogOddsNode::Leaf(None),
+            hit: log_odds(parameters.hit_probability),
+            miss: log_odds(parameters.miss_probability),
+            clamping_min: log_odds(parameters.clamping_min),
+            clamping_max: log_odds(parameters.clamping_max),
+            occupancy_threshold: log_odds(parameters.occupancy_threshold),
+        };
+
+        let size = octree.tree.size();
+
+        for (node, leaf) in octree.tree.layers[0].iter().zip(&octree.tree.leafs) {
+            for index in leaf.get_occupied_indexes() {
+                if let Ok(local_coords) = MortonCode::from_u8(index).decode() {
+                    let clamping_max = octree.clamping_max;
+                    octree
+                        .log_odds
+                        .update(node.position + local_coords, size, |_| clamping_max);
+                }
+            }
+        }
+
+        octree
+    }
+
+    /// The sparse voxel octree containing voxels above the occupancy threshold.
+    #[must_use]
+    pub fn tree(&self) -> &SparseVoxelOctree {
+        &self.tree
+    }
+
+    /// Integrates a single hit (`true`) or miss (`false`) observation of the voxel at a worldspace
+    /// position.
+    ///
+    /// Returns links of the underlying tree whose passability changed.
+    pub fn update_voxel(&mut self, position: Vec3, hit: bool) -> Vec<SparseVoxelOctreeLink> {
+        match self.tree.voxel_coordinates(position) {
+            Some(coordinates) => self.update_voxel_at(coordinates, hit),
+            None => Vec::new(),
+        }
+    }
+
+    /// Same as [`OccupancyOctree::update_voxel`] but with voxel coordinates relative to the origin.
+    pub(crate) fn update_voxel_at(
+        &mut self,
+        coordinates: UVec3,
+        hit: bool,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        let mut changed = Vec::new();
+
+        self.apply_voxel_update(coordinates, hit, &mut changed);
+        self.tree.commit_changes(&changed);
+
+        changed
+    }
+
+    /// Same as [`OccupancyOctree::update_voxel_at`] but only adds the changed links to `changed`.
+    fn apply_voxel_update(
+        &mut self,
+        coordinates: UVec3,
+        hit: bool,
+        changed: &mut Vec<SparseVoxelOctreeLink>,
+    ) {
+        let delta = if hit { self.hit } else { self.miss };
+        let (min, max) = (self.clamping_min, self.clamping_max);
+
+        let (previous, current) = self
+            .log_odds
+            .update(coordinates, self.tree.size(), |value| {
+                (value.unwrap_or(0.0) + delta).clamp(min, max)
+            });
+
+        let state = |value: f32| {
+            if value >= self.occupancy_threshold {
+                VoxelState::Occupied
+            } else {
+                VoxelState::Free
+            }
+        };
+
+        let previous_state = previous.map_or(VoxelState::Unknown, state);
+        let current_state = state(current);
+
+        if previous_state != current_state {
+            self.tree
+                .apply_voxel_state(coordinates, current_state, changed);
+        }
+    }
+
+    /// Returns the occupancy probability of the voxel at a worldspace position.
+    ///
+    /// Returns `None` if the voxel was never observed or is outside of the octree.
+    #[must_use]
+    pub fn occupancy(&self, position: Vec3) -> Option<f32> {
+        let coordinates = self.tree.voxel_coordinates(position)?;
+
+        self.log_odds
+            .get(coordinates, self.tree.size())
+            .map(probability)
+    }
+
+    /// Returns true if the occupancy probability of the voxel at a worldspace position is at or
+    /// above the occupancy threshold.
+    #[must_use]
+    pub fn is_occupied(&self, position: Vec3) -> bool {
+        self.tree
+            .voxel_coordinates(position)
+            .and_then(|coordinates| self.log_odds.get(coordinates, self.tree.size()))
+            .is_some_and(|value| value >= self.occupancy_threshold)
+    }
+
+    /// Integrates a scan of a range sensor located at `sensor_origin`.
+    ///
+    /// Voxels containing the `endpoints` are updated as occupied and all voxels traversed by the
+    /// rays from the sensor to the endpoints are updated as free. Each voxel is updated at most
+    /// once per scan and occupied updates take precedence over free ones.
+    ///
+    /// Rays longer than `max_range` are truncated and their endpoints are not marked as occupied.
+    /// A negative `max_range` means that the range is unlimited.
+    ///
+    /// Rays are traversed in parallel for large scans.
+    ///
+    /// Returns links of the underlying tree whose passability changed.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{OccupancyOctree, OccupancyParameters};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut octree = OccupancyOctree::new(
+    ///     1.0,
+    ///     Vec3::new(-8.0, -8.0, -8.0),
+    ///     Vec3::new(8.0, 8.0, 8.0),
+    ///     OccupancyParameters::default(),
+    /// );
+    ///
+    /// octree.insert_scan(Vec3::new(0.5, 0.5, 0.5), &[Vec3::new(5.5, 0.5, 0.5)], -1.0);
+    ///
+    /// assert!(octree.is_occupied(Vec3::new(5.5, 0.5, 0.5)));
+    /// assert!(octree.occupancy(Vec3::new(3.5, 0.5, 0.5)).unwrap() < 0.5);
+    /// ```
+    pub fn insert_scan(
+        &mut self,
+        sensor_origin: Vec3,
+        endpoints: &[Vec3],
+        max_range: f32,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        let (free, occupied) = self.compute_scan_update(sensor_origin, endpoints, max_range);
+
+        let mut changed = Vec::new();
+
+        for coordinates in occupied {
+            self.apply_voxel_update(coordinates, true, &mut changed);
+        }
+
+        for coordinates in free {
+            self.apply_voxel_update(coordinates, false, &mut changed);
+        }
+
+        self.tree.commit_changes(&changed);
+
+        changed
+    }
+
+    /// Collects de-duplicated free and occupied voxels of a scan.
+    fn compute_scan_update(
+        &self,
+        sensor_origin: Vec3,
+        endpoints: &[Vec3],
+        max_range: f32,
+    ) -> (HashSet<UVec3>, HashSet<UVec3>) {
+        const MIN_RAYS_PER_THREAD: usize = 256;
+
+        let threads = std::thread::available_parallelism()
+            .map_or(1, std::num::NonZeroUsize::get)
+            .min(endpoints.len().div_ceil(MIN_RAYS_PER_THREAD))
+            .max(1);
+
+        let (mut free, occupied) = if threads == 1 {
+            self.cast_rays(sensor_origin, endpoints, max_range)
+        } else {
+            let chunk_size = endpoints.len().div_ceil(threads);
+
+            std::thread::scope(|scope| {
+                let handles = endpoints
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        scope.spawn(move || self.cast_rays(sensor_origin, chunk, max_range))
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut free = HashSet::new();
+                let mut occupied = HashSet::new();
+
+                for handle in handles {
+                    let (chunk_free, chunk_occupied) =
+                        handle.join().expect("ray casting thread panicked");
+
+                    free.extend(chunk_free);
+                    occupied.extend(chunk_occupied);
+                }
+
+                (free, occupied)
+            })
+        };
+
+        free.retain(|coordinates| !occupied.contains(coordinates));
+
+        (free, occupied)
+    }
+
+    /// Traverses rays from the sensor to the endpoints and collects the voxels they touch.
+    fn cast_rays(
+        &self,
+        sensor_origin: Vec3,
+        endpoints: &[Vec3],
+        max_range: f32,
+    ) -> (HashSet<UVec3>, HashSet<UVec3>) {
+        let mut free = HashSet::new();
+        let mut occupied = HashSet::new();
+
+        let size = self.tree.size();
+        let to_voxel_space =
+            |position: Vec3| position / self.tree.voxel_size - self.tree.origin.as_vec3();
+        let in_bounds =
+            |voxel: IVec3| voxel.min_element() >= 0 && voxel.as_uvec3().max_element() < size;
+
+        let from = to_voxel_space(sensor_origin);
+
+        for endpoint in endpoints {
+            let mut to = *endpoint;
+            let mut is_hit = true;
+
+            let distance = sensor_origin.distance(to);
+            if max_range >= 0.0 && distance > max_range {
+                to = sensor_origin + (to - sensor_origin) * (max_range / distance);
+                is_hit = false;
+            }
+
+            let to = to_voxel_space(to);
+
+            traverse_voxels(from, to, |voxel| {
+                if in_bounds(voxel) {
+                    free.insert(voxel.as_uvec3());
+                }
+            });
+
+            let end_voxel = to.floor().as_ivec3();
+            if is_hit && in_bounds(end_voxel) {
+                occupied.insert(end_voxel.as_uvec3());
+            }
+        }
+
+        (free, occupied)
+    }
+
+    /// Number of nodes used to store the log-odds values after pruning.
+    #[must_use]
+    pub fn log_odds_node_count(&self) -> usize {
+        self.log_odds.count()
+    }
+}
+
+/// Node of the octree storing log-odds values.
+///
+/// A leaf node holds a single value for the whole cube it covers. `None` means that the cube
+/// was never observed.
+enum LogOddsNode {
+    Leaf(Option<f32>),
+    Inner(Box<[LogOddsNode; 8]>),
+}
+
+impl LogOddsNode {
+    /// Updates the value of a single voxel at `coordinates` relative to this node of `size`.
+    ///
+    /// Returns the previous and the new value.
+    fn update(
+        &mut self,
+        coordinates: UVec3,
+        size: u32,
+        f: impl FnOnce(Option<f32>) -> f32,
+    ) -> (Option<f32>, f32) {
+        match self {
+            LogOddsNode::Leaf(value) if size <= 1 => {
+                let previous = *value;
+                let current = f(previous);
+                *value = Some(current);
+
+                (previous, current)
+            }
+            LogOddsNode::Leaf(value) => {
+                let value = *value;
+                *self =
+                    LogOddsNode::Inner(Box::new(std::array::from_fn(|_| LogOddsNode::Leaf(value))));
+
+                self.update(coordinates, size, f)
+            }
+            LogOddsNode::Inner(children) => {
+                let half = size / 2;
+                let result = children[Self::child_index(coordinates, half)].update(
+                    coordinates % half,
+                    half,
+                    f,
+                );
+
+                self.prune();
+
+                result
+            }
+        }
+    }
+
+    /// Returns the value of the voxel at `coordinates` relative to this node of `size`.
+    fn get(&self, coordinates: UVec3, size: u32) -> Option<f32> {
+        match self {
+            LogOddsNode::Leaf(value) => *value,
+            LogOddsNode::Inner(children) => {
+                let half = size / 2;
+                children[Self::child_index(coordinates, half)].get(coordinates % half, half)
+            }
+        }
+    }
+
+    /// Collapses the children into a single leaf if all of them hold the same value.
+    fn prune(&mut self) {
+        let LogOddsNode::Inner(children) = self else {
+            return;
+        };
+
+        let LogOddsNode::Leaf(first) = children[0] else {
+            return;
+        };
+
+        let uniform = children.iter().all(|child| match child {
+            LogOddsNode::Leaf(value) => value.map(f32::to_bits) == first.map(f32::to_bits),
+            LogOddsNode::Inner(_) => false,
+        });
+
+        if uniform {
+            *self = LogOddsNode::Leaf(first);
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            LogOddsNode::Leaf(_) => 1,
+            LogOddsNode::Inner(children) => 1 + children.iter().map(Self::count).sum::<usize>(),
+        }
+    }
+
+    /// Index of the child in Morton order containing `coordinates`.
+    #[inline]
+    fn child_index(coordinates: UVec3, half: u32) -> usize {
+        let offset = coordinates / half;
+        (offset.x + offset.y * 2 + offset.z * 4) as usize
+    }
+}
+
+/// Visits all voxels traversed by a ray between two points in voxel space, excluding the voxel
+/// containing the end point.
+///
+/// Based on "A Fast Voxel Traversal Algorithm for Ray Tracing" by Amanatides and Woo.
+fn traverse_voxels(from: Vec3, to: Vec3, mut visit: impl FnMut(IVec3)) {
+    let mut voxel = from.floor().as_ivec3();
+    let end = to.floor().as_ivec3();
+
+    let direction = to - from;
+    let step = IVec3::new(
+        signum(direction.x),
+        signum(direction.y),
+        signum(direction.z),
+    );
+
+    let delta = (Vec3::ONE / direction).abs();
+    let mut max = Vec3::new(
+        first_boundary(from.x, voxel.x, direction.x),
+        first_boundary(from.y, voxel.y, direction.y),
+        first_boundary(from.z, voxel.z, direction.z),
+    );
+
+    // Each step moves one voxel closer to the end along a single axis.
+    let mut steps = (end - voxel).abs().to_array().iter().sum::<i32>();
+
+    while voxel != end && steps > 0 {
+        visit(voxel);
+
+        if max.x < max.y && max.x < max.z {
+            voxel.x += step.x;
+            max.x += delta.x;
+        } else if max.y < max.z {
+            voxel.y += step.y;
+            max.y += delta.y;
+        } else {
+            voxel.z += step.z;
+            max.z += delta.z;
+        }
+
+        steps -= 1;
+    }
+}
+
+/// Ray parameter at which the ray crosses the first voxel boundary along an axis.
+#[inline]
+fn first_boundary(from: f32, voxel: i32, direction: f32) -> f32 {
+    #[allow(clippy::cast_precision_loss)]
+    let voxel = voxel as f32;
+
+    if direction > 0.0 {
+        (voxel + 1.0 - from) / direction
+    } else if direction < 0.0 {
+        (voxel - from) / direction
+    } else {
+        f32::INFINITY
+    }
+}
+
+#[inline]
+fn signum(value: f32) -> i32 {
+    if value > 0.0 {
+        1
+    } else if value < 0.0 {
+        -1
+    } else {
+        0
+    }
+}
+
+#[inline]
+fn log_odds(probability: f32) -> f32 {
+    (probability / (1.0 - probability)).ln()
+}
+
+#[inline]
+fn probability(log_odds: f32) -> f32 {
+    1.0 - 1.0 / (1.0 + log_odds.exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use super::*;
+
+    fn create_octree() -> OccupancyOctree {
+        OccupancyOctree::new(
+            1.0,
+            Vec3::new(-8.0, -8.0, -8.0),
+            Vec3::new(8.0, 8.0, 8.0),
+            OccupancyParameters::default(),
+        )
+    }
+
+    #[test]
+    fn test_log_odds_round_trip() {
+        assert!((probability(log_odds(0.7)) - 0.7).abs() < 1e-5);
+        assert!(log_odds(0.5).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_unobserved_voxel() {
+        let octree = create_octree();
+
+        assert_eq!(octree.occupancy(Vec3::new(0.5, 0.5, 0.5)), None);
+        assert!(!octree.is_occupied(Vec3::new(0.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_hits_and_misses_toggle_occupancy() {
+        let mut octree = create_octree();
+        let position = Vec3::new(2.5, 0.5, 0.5);
+
+        let changed = octree.update_voxel(position, true);
+
+        assert!(!changed.is_empty());
+        assert!(octree.is_occupied(position));
+        assert!(!octree
+            .tree()
+            .is_in_line_of_sight(Vec3::new(0.5, 0.5, 0.5), Vec3::new(4.5, 0.5, 0.5)));
+
+        octree.update_voxel(position, false);
+        octree.update_voxel(position, false);
+        octree.update_voxel(position, false);
+
+        assert!(!octree.is_occupied(position));
+        assert!(octree
+            .tree()
+            .is_in_line_of_sight(Vec3::new(0.5, 0.5, 0.5), Vec3::new(4.5, 0.5, 0.5)));
+    }
+
+    #[test]
+    fn test_occupancy_is_clamped() {
+        let mut octree = create_octree();
+        let position = Vec3::new(0.5, 0.5, 0.5);
+
+        for _ in 0..100 {
+            octree.update_voxel(position, true);
+        }
+
+        let occupancy = octree.occupancy(position).unwrap();
+        assert!((occupancy - OccupancyParameters::default().clamping_max).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_uniform_children_are_pruned() {
+        let mut octree = create_octree();
+
+        for _ in 0..10 {
+            for x in 0..2_u8 {
+                for y in 0..2_u8 {
+                    for z in 0..2_u8 {
+                        let position = Vec3::new(f32::from(x), f32::from(y), f32::from(z));
+                        octree.update_voxel(position + 0.5, false);
+                    }
+                }
+            }
+        }
+
+        // The root, its 8 children and 8 grandchildren, followed by 8 nodes of size 2
+        // where the fully observed 2x2x2 cube collapsed into a single one of them.
+        assert_eq!(octree.log_odds_node_count(), 1 + 8 + 8 + 8);
+    }
+
+    #[test]
+    fn test_successors_avoid_occupied_voxels() {
+        let mut octree = create_octree();
+
+        for _ in 0..3 {
+            octree.update_voxel(Vec3::new(1.5, 0.5, 0.5), true);
+        }
+
+        let tree = octree.tree();
+        let link = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+        let blocked = tree.find_node(Vec3::new(1.5, 0.5, 0.5)).unwrap();
+
+        assert!(!tree.successors(link).contains(&blocked));
+    }
+
+    #[test]
+    fn test_traverse_voxels() {
+        let mut visited = Vec::new();
+
+        traverse_voxels(
+            Vec3::new(0.5, 0.5, 0.5),
+            Vec3::new(3.5, 1.5, 0.5),
+            |voxel| {
+                visited.push(voxel);
+            },
+        );
+
+        assert_eq!(
+            visited,
+            vec![
+                IVec3::new(0, 0, 0),
+                IVec3::new(1, 0, 0),
+                IVec3::new(1, 1, 0),
+                IVec3::new(2, 1, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_insert_scan_clears_free_space() {
+        let mut octree = create_octree();
+
+        for _ in 0..3 {
+            octree.update_voxel(Vec3::new(3.5, 0.5, 0.5), true);
+        }
+
+        let changed = octree.insert_scan(
+            Vec3::new(0.5, 0.5, 0.5),
+            &[Vec3::new(6.5, 0.5, 0.5), Vec3::new(6.5, 0.5, 0.5)],
+            -1.0,
+        );
+
+        assert!(!changed.is_empty());
+        assert!(octree.is_occupied(Vec3::new(6.5, 0.5, 0.5)));
+        assert!(octree.is_occupied(Vec3::new(3.5, 0.5, 0.5)));
+
+        // A single miss was integrated despite the duplicate endpoint.
+        let occupancy = octree.occupancy(Vec3::new(1.5, 0.5, 0.5)).unwrap();
+        assert!((occupancy - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_insert_scan_respects_max_range() {
+        let mut octree = create_octree();
+
+        octree.insert_scan(Vec3::new(0.5, 0.5, 0.5), &[Vec3::new(6.5, 0.5, 0.5)], 3.0);
+
+        assert!(octree.occupancy(Vec3::new(2.5, 0.5, 0.5)).is_some());
+        assert_eq!(octree.occupancy(Vec3::new(4.5, 0.5, 0.5)), None);
+        assert_eq!(octree.occupancy(Vec3::new(6.5, 0.5, 0.5)), None);
+    }
+
+    #[test]
+    fn test_insert_scan_in_parallel() {
+        let mut octree = create_octree();
+
+        let endpoints = (0..2048_u16)
+            .map(|i| Vec3::new(7.5, f32::from(i % 8) - 3.5, f32::from(i / 8 % 8) - 3.5))
+            .collect::<Vec<_>>();
+
+        octree.insert_scan(Vec3::new(0.5, 0.5, 0.5), &endpoints, -1.0);
+
+        for endpoint in &endpoints {
+            assert!(octree.is_occupied(*endpoint));
+        }
+
+        assert!(!octree.is_occupied(Vec3::new(4.5, 0.5, 0.5)));
+    }
+}
@@ -0,0 +1,414 @@
+// Resource: https://github.com/OctoMap/octomap/blob/devel/octomap/include/octomap/OccupancyOcTreeBase.hxx
+
+use std::{error::Error, fs::File, io::Read, path::Path};
+
+use bevy_math::{IVec3, UVec3, Vec3};
+
+use crate::{SparseVoxelOctree, SparseVoxelOctreeBuilder, VoxelState};
+
+const BINARY_HEADER: &str = "# Octomap OcTree binary file";
+const FULL_HEADER: &str = "# Octomap OcTree file";
+
+/// Depth of `OctoMap` trees. Voxels at this depth have the size of the resolution.
+const TREE_DEPTH: u32 = 16;
+
+/// Node of an `OctoMap` without children.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct OctoMapLeaf {
+    /// Minimal corner in voxel coordinates.
+    position: IVec3,
+    /// Size in voxels along a single axis. Pruned nodes cover more than one voxel.
+    size: u32,
+    occupied: bool,
+}
+
+impl SparseVoxelOctree {
+    /// Reads an `OctoMap` from a `.bt` (binary occupancy) or `.ot` file.
+    ///
+    /// See [`SparseVoxelOctree::read_octomap`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use svo_rs::SparseVoxelOctree;
+    ///
+    /// let octree = SparseVoxelOctree::load_octomap("fr_campus.bt").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the file can't be read or is not a supported `OctoMap` file.
+    pub fn load_octomap(path: impl AsRef<Path>) -> Result<Self, OctoMapError> {
+        let file = File::open(path).map_err(|e| OctoMapError(e.to_string()))?;
+
+        Self::read_octomap(file)
+    }
+
+    /// Reads an `OctoMap` in the `.bt` (binary occupancy) or `.ot` format.
+    ///
+    /// The format is detected from the header. The resolution of the map becomes the voxel size
+    /// of the octree and voxel coordinates are kept, so positions in the map and in the octree
+    /// match. Occupied nodes are filled, free nodes are free and space that is not part of the
+    /// map is [`VoxelState::Unknown`].
+    ///
+    /// Only `.ot` files of the `OcTree` type are supported. Nodes of these files are occupied
+    /// when their log-odds value is at least zero, which is the default `OctoMap` threshold.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctree, VoxelState};
+    /// use bevy_math::Vec3;
+    ///
+    /// let file = b"# Octomap OcTree binary file\nid OcTree\nsize 0\nres 0.1\ndata\n";
+    /// let octree = SparseVoxelOctree::read_octomap(file.as_slice()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     octree.voxel_state(Vec3::new(0.05, 0.05, 0.05)),
+    ///     Some(VoxelState::Unknown)
+    /// );
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the data can't be read or is not a supported `OctoMap` file.
+    pub fn read_octomap(mut reader: impl Read) -> Result<Self, OctoMapError> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(|e| OctoMapError(e.to_string()))?;
+
+        let mut cursor = Cursor { data: &data, at: 0 };
+
+        let binary = match cursor.read_line()?.trim_end() {
+            BINARY_HEADER => true,
+            FULL_HEADER => false,
+            header => return Err(OctoMapError(format!("Unknown header {header}"))),
+        };
+
+        let mut id = None;
+        let mut size = None;
+        let mut resolution = None;
+
+        loop {
+            let line = cursor.read_line()?;
+            let mut parts = line.split_whitespace();
+
+            match (parts.next(), parts.next()) {
+                (Some("data"), _) => break,
+                (Some("id"), Some(value)) => id = Some(value.to_string()),
+                (Some("size"), Some(value)) => size = value.parse::<usize>().ok(),
+                (Some("res"), Some(value)) => resolution = value.parse::<f32>().ok(),
+                _ => {}
+            }
+        }
+
+        if id.as_deref() != Some("OcTree") {
+            return Err(OctoMapError(format!("Unsupported tree type {id:?}")));
+        }
+
+        let resolution = resolution
+            .filter(|resolution| *resolution > 0.0)
+            .ok_or(OctoMapError("Missing or invalid resolution".to_string()))?;
+        let size = size.ok_or(OctoMapError("Missing tree size".to_string()))?;
+
+        let mut leaves = Vec::new();
+
+        if size > 0 {
+            let position = IVec3::splat(-(1 << (TREE_DEPTH - 1)));
+
+            if binary {
+                read_binary_node(&mut cursor, position, 1 << TREE_DEPTH, &mut leaves)?;
+            } else {
+                read_full_node(&mut cursor, position, 1 << TREE_DEPTH, &mut leaves)?;
+            }
+        }
+
+        Ok(build_octree(resolution, &leaves))
+    }
+}
+
+/// Reads a node of a `.bt` file. Each child is encoded by 2 bits: `01` free, `10` occupied,
+/// `11` inner node and `00` unknown. Inner children follow in the order of their index.
+fn read_binary_node(
+    cursor: &mut Cursor,
+    position: IVec3,
+    size: u32,
+    leaves: &mut Vec<OctoMapLeaf>,
+) -> Result<(), OctoMapError> {
+    if size == 1 {
+        return Err(OctoMapError("Tree is deeper than 16 levels".to_string()));
+    }
+
+    let children = u16::from_le_bytes([cursor.read_u8()?, cursor.read_u8()?]);
+    let child_size = size / 2;
+
+    for child in 0..8 {
+        let child_position = child_position(position, child_size, child);
+
+        match (children >> (child * 2)) & 0b11 {
+            0b01 => leaves.push(OctoMapLeaf {
+                position: child_position,
+                size: child_size,
+                occupied: false,
+            }),
+            0b10 => leaves.push(OctoMapLeaf {
+                position: child_position,
+                size: child_size,
+                occupied: true,
+            }),
+            _ => {}
+        }
+    }
+
+    for child in 0..8 {
+        if (children >> (child * 2)) & 0b11 == 0b11 {
+            read_binary_node(
+                cursor,
+                child_position(position, child_size, child),
+                child_size,
+                leaves,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a node of a `.ot` file. Each node stores its log-odds value followed by a bitmask of
+/// existing children, which follow in the order of their index.
+fn read_full_node(
+    cursor: &mut Cursor,
+    position: IVec3,
+    size: u32,
+    leaves: &mut Vec<OctoMapLeaf>,
+) -> Result<(), OctoMapError> {
+    let log_odds = f32::from_le_bytes([
+        cursor.read_u8()?,
+        cursor.read_u8()?,
+        cursor.read_u8()?,
+        cursor.read_u8()?,
+    ]);
+    let children = cursor.read_u8()?;
+
+    if children == 0 {
+        leaves.push(OctoMapLeaf {
+            position,
+            size,
+            occupied: log_odds >= 0.0,
+        });
+
+        return Ok(());
+    }
+
+    if size == 1 {
+        return Err(OctoMapError("Tree is deeper than 16 levels".to_string()));
+    }
+
+    for child in 0..8 {
+        if children & (1 << child) != 0 {
+            read_full_node(
+                cursor,
+                child_position(position, size / 2, child),
+                size / 2,
+                leaves,
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Position of a child in voxel coordinates. Children are indexed the same way as in
+/// [`crate::consts::OFFSETS_IN_MORTON_CODE_ORDER`].
+fn child_position(position: IVec3, child_size: u32, child: u16) -> IVec3 {
+    let offset = IVec3::new(
+        i32::from(child & 1),
+        i32::from((child >> 1) & 1),
+        i32::from((child >> 2) & 1),
+    );
+
+    #[allow(clippy::cast_possible_wrap)]
+    let child_size = child_size as i32;
+
+    position + offset * child_size
+}
+
+#[allow(clippy::cast_possible_wrap, clippy::cast_precision_loss)]
+fn build_octree(resolution: f32, leaves: &[OctoMapLeaf]) -> SparseVoxelOctree {
+    let mut min = leaves
+        .iter()
+        .fold(IVec3::MAX, |min, leaf| min.min(leaf.position));
+    let mut max = leaves.iter().fold(IVec3::MIN, |max, leaf| {
+        max.max(leaf.position + IVec3::splat(leaf.size as i32))
+    });
+
+    if leaves.is_empty() {
+        min = IVec3::ZERO;
+        max = IVec3::ZERO;
+    }
+
+    // The octree needs at least two layers to have a root node above the leaf nodes.
+    let max = max.max(min + IVec3::splat(8));
+
+    let mut builder = SparseVoxelOctreeBuilder::new(resolution);
+    builder.set_unknown_by_default(true);
+    // Shifted by half a voxel so rounding errors don't extend the bounds.
+    builder.set_bounds(
+        (min.as_vec3() + Vec3::splat(0.5)) * resolution,
+        (max.as_vec3() - Vec3::splat(0.5)) * resolution,
+    );
+
+    let mut octree = builder.build();
+    let mut changed = Vec::new();
+
+    for leaf in leaves {
+        let position = (leaf.position - octree.origin).as_uvec3();
+        let state = if leaf.occupied {
+            VoxelState::Occupied
+        } else {
+            VoxelState::Free
+        };
+
+        octree.apply_box_state(
+            position,
+            position + UVec3::splat(leaf.size),
+            state,
+            &mut changed,
+        );
+    }
+
+    octree.commit_changes(&changed);
+
+    octree
+}
+
+/// Reads the header lines and binary data of a file.
+struct Cursor<'a> {
+    data: &'a [u8],
+    at: usize,
+}
+
+impl Cursor<'_> {
+    fn read_u8(&mut self) -> Result<u8, OctoMapError> {
+        let value = self
+            .data
+            .get(self.at)
+            .ok_or(OctoMapError("Unexpected end of data".to_string()))?;
+
+        self.at += 1;
+
+        Ok(*value)
+    }
+
+    fn read_line(&mut self) -> Result<String, OctoMapError> {
+        let rest = &self.data[self.at..];
+        let end = rest
+            .iter()
+            .position(|byte| *byte == b'\n')
+            .ok_or(OctoMapError("Unexpected end of header".to_string()))?;
+
+        self.at += end + 1;
+
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+}
+
+#[derive(Debug)]
+pub struct OctoMapError(String);
+
+impl Error for OctoMapError {}
+
+impl std::fmt::Display for OctoMapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OctoMap error: {:#?}", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(binary: bool) -> Vec<u8> {
+        let header = if binary { BINARY_HEADER } else { FULL_HEADER };
+
+        format!("{header}\n# comment\nid OcTree\nsize 3\nres 0.25\ndata\n").into_bytes()
+    }
+
+    #[test]
+    fn test_read_binary_octomap() {
+        let mut data = header(true);
+
+        // Root with an inner node as the last child, followed by inner first children down to
+        // a node of 4x4x4 voxels.
+        data.extend(0b1100_0000_0000_0000_u16.to_le_bytes());
+
+        for _ in 0..13 {
+            // Inner node as the first child.
+            data.extend(0b11_u16.to_le_bytes());
+        }
+
+        // Free child 0 and occupied child 1, both 2x2x2 voxels.
+        data.extend(0b1001_u16.to_le_bytes());
+
+        let octree = SparseVoxelOctree::read_octomap(data.as_slice()).unwrap();
+
+        assert!((octree.voxel_size - 0.25).abs() < f32::EPSILON);
+
+        let state = |x: f32, y: f32, z: f32| octree.voxel_state(Vec3::new(x, y, z) * 0.25);
+
+        assert_eq!(state(0.5, 0.5, 0.5), Some(VoxelState::Free));
+        assert_eq!(state(1.5, 1.5, 1.5), Some(VoxelState::Free));
+        assert_eq!(state(2.5, 0.5, 0.5), Some(VoxelState::Occupied));
+        assert_eq!(state(3.5, 1.5, 1.5), Some(VoxelState::Occupied));
+        assert_eq!(state(0.5, 2.5, 0.5), Some(VoxelState::Unknown));
+        assert_eq!(state(6.5, 6.5, 6.5), Some(VoxelState::Unknown));
+    }
+
+    #[test]
+    fn test_read_full_octomap() {
+        let mut data = header(false);
+
+        let mut node = |log_odds: f32, children: u8| {
+            data.extend(log_odds.to_le_bytes());
+            data.push(children);
+        };
+
+        // Root with the last child, then first children down to a node of 4x4x4 voxels.
+        node(2.0, 0b1000_0000);
+
+        for _ in 0..13 {
+            node(2.0, 0b1);
+        }
+
+        // Children 2x2x2 voxels: occupied, free and a subdivided one with a single free voxel.
+        node(2.0, 0b1011);
+        node(2.0, 0);
+        node(-2.0, 0);
+        node(-2.0, 0b1000_0000);
+        node(-2.0, 0);
+
+        let octree = SparseVoxelOctree::read_octomap(data.as_slice()).unwrap();
+
+        let state = |x: f32, y: f32, z: f32| octree.voxel_state(Vec3::new(x, y, z) * 0.25);
+
+        assert_eq!(state(0.5, 0.5, 0.5), Some(VoxelState::Occupied));
+        assert_eq!(state(2.5, 0.5, 0.5), Some(VoxelState::Free));
+        assert_eq!(state(3.5, 3.5, 1.5), Some(VoxelState::Free));
+        assert_eq!(state(2.5, 2.5, 0.5), Some(VoxelState::Unknown));
+        assert_eq!(state(0.5, 2.5, 0.5), Some(VoxelState::Unknown));
+        assert_eq!(state(0.5, 0.5, 2.5), Some(VoxelState::Unknown));
+    }
+
+    #[test]
+    fn test_read_invalid_octomap() {
+        assert!(SparseVoxelOctree::read_octomap(b"# Not an octomap\n".as_slice()).is_err());
+
+        let mut truncated = header(true);
+        truncated.push(0b11);
+
+        assert!(SparseVoxelOctree::read_octomap(truncated.as_slice()).is_err());
+
+        let color = b"# Octomap OcTree file\nid ColorOcTree\nsize 1\nres 0.1\ndata\n";
+
+        assert!(SparseVoxelOctree::read_octomap(color.as_slice()).is_err());
+    }
+}
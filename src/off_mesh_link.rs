@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+
+use bevy_math::Vec3;
+
+use crate::{path_cache::Change, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Identifier of an off-mesh link returned by [`SparseVoxelOctree::add_off_mesh_link`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OffMeshLinkId(u32);
+
+/// User defined connection between two points in world space, such as a teleporter, a hatch
+/// or an elevator.
+///
+/// The points are resolved to the nodes containing them whenever the octree changes, so the
+/// connection survives incremental updates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OffMeshLink {
+    /// Point where the link starts.
+    pub start: Vec3,
+    /// Point where the link ends.
+    pub end: Vec3,
+    /// If true, the link can be also traversed from the end to the start.
+    pub bidirectional: bool,
+    /// Cost of traversing the link. It replaces the distance between the nodes.
+    pub cost: f32,
+    /// Value identifying the link for the user, for example the animation to play.
+    pub user_id: u64,
+}
+
+/// Off-mesh links of an octree together with the links between nodes they resolve to.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct OffMeshLinks {
+    links: HashMap<OffMeshLinkId, OffMeshLink>,
+    /// Node where an off-mesh link starts to the node where it ends.
+    resolved: HashMap<SparseVoxelOctreeLink, Vec<(SparseVoxelOctreeLink, OffMeshLinkId)>>,
+    /// Node where an off-mesh link ends to the node where it starts.
+    reversed: HashMap<SparseVoxelOctreeLink, Vec<(SparseVoxelOctreeLink, OffMeshLinkId)>>,
+    /// Nodes the start and the end of an off-mesh link resolved to.
+    ends: HashMap<OffMeshLinkId, (SparseVoxelOctreeLink, SparseVoxelOctreeLink)>,
+    /// Off-mesh links with an end in a node or in one of the subnodes of a leaf node.
+    by_node: HashMap<SparseVoxelOctreeLink, Vec<OffMeshLinkId>>,
+    /// Lowest ratio between the cost of a resolved link and the distance between the centers of
+    /// its nodes, if any is lower than 1.
+    heuristic_scale: Option<f32>,
+    /// Lower bound of the factor set by [`SparseVoxelOctree::set_min_heuristic_scale`].
+    min_heuristic_scale: f32,
+    next_id: u32,
+}
+
+impl OffMeshLinks {
+    /// Nodes reachable from a node through off-mesh links.
+    pub(crate) fn targets(
+        &self,
+        from: SparseVoxelOctreeLink,
+    ) -> impl Iterator<Item = SparseVoxelOctreeLink> + '_ {
+        self.resolved
+            .get(&from)
+            .into_iter()
+            .flatten()
+            .map(|(to, _)| *to)
+    }
+
+    /// Nodes from which a node is reachable through off-mesh links.
+    pub(crate) fn sources(
+        &self,
+        to: SparseVoxelOctreeLink,
+    ) -> impl Iterator<Item = SparseVoxelOctreeLink> + '_ {
+        self.reversed
+            .get(&to)
+            .into_iter()
+            .flatten()
+            .map(|(from, _)| *from)
+    }
+
+    /// Nodes the starts and the ends of the off-mesh links resolved to.
+    pub(crate) fn ends(
+        &self,
+    ) -> impl Iterator<Item = (SparseVoxelOctreeLink, SparseVoxelOctreeLink)> + '_ {
+        self.ends.values().copied()
+    }
+
+    /// Removes the resolved ends of an off-mesh link.
+    fn unresolve(&mut self, id: OffMeshLinkId) {
+        let Some((start, end)) = self.ends.remove(&id) else {
+            return;
+        };
+
+        for from in [start, end] {
+            for map in [&mut self.resolved, &mut self.reversed] {
+                if let Some(others) = map.get_mut(&from) {
+                    others.retain(|(_, other)| *other != id);
+
+                    if others.is_empty() {
+                        map.remove(&from);
+                    }
+                }
+            }
+
+            if let Some(ids) = self.by_node.get_mut(&node_of(from)) {
+                ids.retain(|other| *other != id);
+
+                if ids.is_empty() {
+                    self.by_node.remove(&node_of(from));
+                }
+            }
+        }
+    }
+
+    /// Factor keeping the distance between nodes an admissible heuristic when off-mesh links
+    /// are cheaper than the distance they cover.
+    #[inline]
+    pub(crate) fn heuristic_scale(&self) -> f32 {
+        self.heuristic_scale
+            .unwrap_or(1.0)
+            .max(self.min_heuristic_scale)
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Adds a connection between two points in world space.
+    ///
+    /// The node containing the end of the link is returned by
+    /// [`SparseVoxelOctree::successors`] of the node containing its start. Links with a point
+    /// outside of the octree are kept, but not used.
+    ///
+    /// A single link cheaper than the distance between its nodes scales down the heuristic of
+    /// all built-in searches by the ratio of its cost to that distance, anywhere in the octree,
+    /// so the found paths stay the cheapest ones. The searches expand more nodes the cheaper the
+    /// link is, a link with a cost of 0 turns them into Dijkstra's algorithm. Use
+    /// [`SparseVoxelOctree::set_min_heuristic_scale`] to bound the slowdown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{OffMeshLink, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    ///
+    /// let start = Vec3::new(-6.0, -6.0, -6.0);
+    /// let end = Vec3::new(6.0, 6.0, 6.0);
+    ///
+    /// octree.add_off_mesh_link(OffMeshLink {
+    ///     start,
+    ///     end,
+    ///     bidirectional: false,
+    ///     cost: 1.0,
+    ///     user_id: 7,
+    /// });
+    ///
+    /// let from = octree.find_node(start).unwrap();
+    /// let to = octree.find_node(end).unwrap();
+    ///
+    /// assert!(octree.successors(from).contains(&to));
+    /// assert!(!octree.successors(to).contains(&from));
+    /// ```
+    pub fn add_off_mesh_link(&mut self, link: OffMeshLink) -> OffMeshLinkId {
+        let id = OffMeshLinkId(self.off_mesh_links.next_id);
+        self.off_mesh_links.next_id += 1;
+
+        self.off_mesh_links.links.insert(id, link);
+        self.resolve_off_mesh_link(id);
+        self.update_heuristic_scale();
+        self.record_off_mesh_link_change(&link);
+
+        id
+    }
+
+    /// Removes an off-mesh link. Returns false if there was no link with the id.
+    pub fn remove_off_mesh_link(&mut self, id: OffMeshLinkId) -> bool {
+        let Some(link) = self.off_mesh_links.links.remove(&id) else {
+            return false;
+        };
+
+        self.off_mesh_links.unresolve(id);
+        self.update_heuristic_scale();
+        self.record_off_mesh_link_change(&link);
+
+        true
+    }
+
+    /// Records the nodes at the ends of an off-mesh link as changed.
+    fn record_off_mesh_link_change(&mut self, link: &OffMeshLink) {
+        let ends = [link.start, link.end]
+            .into_iter()
+            .filter_map(|point| self.find_node(point))
+            .collect();
+
+        self.record_change(Change::Links(ends));
+    }
+
+    /// Sets the lowest factor the heuristic of the built-in searches is scaled down to by cheap
+    /// off-mesh links, between 0 and 1. Defaults to 0.
+    ///
+    /// Higher values keep the searches fast when links are much cheaper than the distance they
+    /// cover, but the found paths may then miss a cheaper path through such a link.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::SparseVoxelOctreeBuilder;
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    /// octree.set_min_heuristic_scale(0.5);
+    /// ```
+    pub fn set_min_heuristic_scale(&mut self, scale: f32) {
+        self.off_mesh_links.min_heuristic_scale = scale.clamp(0.0, 1.0);
+    }
+
+    /// Returns an off-mesh link by its id.
+    #[must_use]
+    pub fn off_mesh_link(&self, id: OffMeshLinkId) -> Option<&OffMeshLink> {
+        self.off_mesh_links.links.get(&id)
+    }
+
+    /// Returns the cheapest off-mesh link leading directly from one node to another together
+    /// with its cost.
+    pub(crate) fn off_mesh_link_between(
+        &self,
+        from: SparseVoxelOctreeLink,
+        to: SparseVoxelOctreeLink,
+    ) -> Option<(OffMeshLinkId, f32)> {
+        self.off_mesh_links
+            .resolved
+            .get(&from)?
+            .iter()
+            .filter(|(target, _)| *target == to)
+            .filter_map(|(_, id)| Some((*id, self.off_mesh_links.links.get(id)?.cost)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    /// Resolves again only the off-mesh links with an end in one of the changed nodes, for
+    /// example because it was subdivided.
+    pub(crate) fn resolve_off_mesh_links_at(&mut self, changed: &[SparseVoxelOctreeLink]) {
+        if self.off_mesh_links.by_node.is_empty() {
+            return;
+        }
+
+        let mut ids = changed
+            .iter()
+            .filter_map(|link| self.off_mesh_links.by_node.get(&node_of(*link)))
+            .flatten()
+            .copied()
+            .collect::<Vec<_>>();
+
+        if ids.is_empty() {
+            return;
+        }
+
+        ids.sort_unstable();
+        ids.dedup();
+
+        for id in ids {
+            self.resolve_off_mesh_link(id);
+        }
+
+        self.update_heuristic_scale();
+    }
+
+    /// Resolves the points of an off-mesh link to the nodes containing them.
+    fn resolve_off_mesh_link(&mut self, id: OffMeshLinkId) {
+        self.off_mesh_links.unresolve(id);
+
+        let Some(link) = self.off_mesh_links.links.get(&id).copied() else {
+            return;
+        };
+
+        let (Some(start), Some(end)) = (self.find_node(link.start), self.find_node(link.end))
+        else {
+            return;
+        };
+
+        let links = &mut self.off_mesh_links;
+
+        links.resolved.entry(start).or_default().push((end, id));
+        links.reversed.entry(end).or_default().push((start, id));
+
+        if link.bidirectional {
+            links.resolved.entry(end).or_default().push((start, id));
+            links.reversed.entry(start).or_default().push((end, id));
+        }
+
+        links.ends.insert(id, (start, end));
+        links.by_node.entry(node_of(start)).or_default().push(id);
+
+        if node_of(end) != node_of(start) {
+            links.by_node.entry(node_of(end)).or_default().push(id);
+        }
+    }
+
+    /// Recomputes the factor of [`OffMeshLinks::heuristic_scale`] from the resolved links.
+    fn update_heuristic_scale(&mut self) {
+        self.off_mesh_links.heuristic_scale = self
+            .off_mesh_links
+            .ends
+            .iter()
+            .filter_map(|(id, (start, end))| {
+                let distance = self
+                    .node_position(*start)
+                    .distance(self.node_position(*end));
+                let cost = self.off_mesh_links.links.get(id)?.cost;
+
+                (distance > 0.0 && cost < distance).then(|| cost.max(0.0) / distance)
+            })
+            .min_by(f32::total_cmp);
+    }
+}
+
+/// Link to the node of a link, which is the leaf node for subnodes.
+fn node_of(link: SparseVoxelOctreeLink) -> SparseVoxelOctreeLink {
+    SparseVoxelOctreeLink::new(link.layer_index, link.node_index, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3};
+
+    use crate::{PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_path_through_off_mesh_link() {
+        let mut voxels = Vec::new();
+
+        for y in 0..16 {
+            for z in 0..16 {
+                voxels.push(UVec3::new(0, y, z));
+            }
+        }
+
+        // Wall splitting the octree into two halves.
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::new(0, -8, -8)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let mut tree = builder.build();
+
+        let start = tree.find_node(Vec3::new(-2.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(2.5, 0.5, 0.5)).unwrap();
+
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_none());
+
+        let hatch = tree.add_off_mesh_link(OffMeshLink {
+            start: Vec3::new(-1.5, 0.5, 0.5),
+            end: Vec3::new(5.5, 0.5, 0.5),
+            bidirectional: true,
+            cost: 3.0,
+            user_id: 42,
+        });
+
+        let path = tree
+            .find_path(goal, start, &PathOptions::default())
+            .unwrap();
+
+        assert_eq!(path.off_mesh_links.len(), 1);
+
+        let (index, id) = path.off_mesh_links[0];
+        assert_eq!(id, hatch);
+        assert_eq!(tree.off_mesh_link(id).unwrap().user_id, 42);
+        assert_eq!(
+            Some(path.links[index]),
+            tree.find_node(Vec3::new(-1.5, 0.5, 0.5))
+        );
+
+        // Subdividing the node containing the end of the link keeps the link connected.
+        tree.set_voxel(Vec3::new(6.5, 3.5, 3.5), true);
+        assert_eq!(
+            tree.find_node(Vec3::new(5.5, 0.5, 0.5))
+                .unwrap()
+                .layer_index,
+            0
+        );
+
+        let start = tree.find_node(Vec3::new(-2.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(2.5, 0.5, 0.5)).unwrap();
+
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_some());
+
+        assert!(tree.remove_off_mesh_link(hatch));
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_cheap_off_mesh_link_keeps_path_optimal() {
+        let mut voxels = Vec::new();
+
+        // Floor below the agents, so they move between single voxels.
+        for x in 0..16 {
+            for y in 0..16 {
+                voxels.push(UVec3::new(x, y, 0));
+            }
+        }
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::new(-8, -8, 6)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let mut tree = builder.build();
+        let options = PathOptions::default();
+
+        let start = tree.find_node(Vec3::new(-6.5, -6.5, 7.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, -6.5, 7.5)).unwrap();
+        let direct = tree.find_path(start, goal, &options).unwrap();
+
+        // Teleporter off to the side, cheaper than going straight, but farther from the goal than
+        // the cost of the direct path.
+        tree.add_off_mesh_link(OffMeshLink {
+            start: Vec3::new(-6.5, -2.5, 7.5),
+            end: Vec3::new(6.5, -2.5, 7.5),
+            bidirectional: false,
+            cost: 0.1,
+            user_id: 0,
+        });
+
+        let distances = tree.distance_field(&[goal], f32::INFINITY, &options);
+        let path = tree.find_path(start, goal, &options).unwrap();
+
+        assert!(tree.off_mesh_links.heuristic_scale() < 1.0);
+        assert_eq!(path.off_mesh_links.len(), 1);
+        assert!(path.cost < direct.cost);
+        assert!((path.cost - distances.distance(start).unwrap()).abs() < 1e-4);
+
+        // A lower bound of the scale keeps the heuristic from dropping any further.
+        tree.set_min_heuristic_scale(0.9);
+
+        assert!((tree.off_mesh_links.heuristic_scale() - 0.9).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_off_mesh_link_index_follows_changed_ends() {
+        let mut voxels = Vec::new();
+
+        // Floor with uniform leaf nodes above it.
+        for x in 0..16 {
+            for y in 0..16 {
+                voxels.push(UVec3::new(x, y, 0));
+            }
+        }
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::new(-8, -8, -8)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let mut tree = builder.build();
+
+        let (start, end) = (Vec3::new(-5.5, -5.5, -2.5), Vec3::new(5.5, 5.5, -2.5));
+        let id = tree.add_off_mesh_link(OffMeshLink {
+            start,
+            end,
+            bidirectional: true,
+            cost: 1.0,
+            user_id: 0,
+        });
+
+        let from = tree.find_node(start).unwrap();
+        let to = tree.find_node(end).unwrap();
+
+        assert_eq!(to.subnode_index, None);
+        assert_eq!(tree.off_mesh_links.ends[&id], (from, to));
+
+        // Changes away from the ends keep them.
+        tree.set_voxel(Vec3::new(0.5, 0.5, 0.5), true).unwrap();
+
+        assert_eq!(tree.off_mesh_links.ends[&id], (from, to));
+
+        // Filling a voxel of the uniform leaf of the end splits it into subnodes.
+        tree.set_voxel(Vec3::new(4.5, 4.5, -2.5), true).unwrap();
+
+        let to = tree.find_node(end).unwrap();
+
+        assert!(to.subnode_index.is_some());
+        assert!(tree.successors(from).contains(&to));
+        assert!(tree.successors(to).contains(&from));
+        assert_eq!(tree.off_mesh_links.sources(to).collect::<Vec<_>>(), [from]);
+        assert_eq!(tree.off_mesh_links.sources(from).collect::<Vec<_>>(), [to]);
+        assert_eq!(tree.off_mesh_links.ends[&id], (from, to));
+
+        assert!(tree.remove_off_mesh_link(id));
+        assert!(tree.off_mesh_links.by_node.is_empty());
+        assert!(tree.off_mesh_links.resolved.is_empty());
+        assert!(tree.off_mesh_links.reversed.is_empty());
+    }
+}
@@ -0,0 +1,442 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink, VolumeShape};
+
+/// Number of changes remembered by an octree. Caches that fall further behind are cleared.
+const CHANGE_LOG_CAPACITY: usize = 256;
+
+/// Change of an octree that may invalidate cached paths.
+#[derive(Debug, Clone)]
+pub(crate) enum Change {
+    /// Links whose state, area or passability changed, including subdivided links.
+    Links(Vec<SparseVoxelOctreeLink>),
+    /// Links overlapping the shape may have changed.
+    Volume(VolumeShape),
+}
+
+/// Latest changes of an octree, numbered by revisions.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ChangeLog {
+    revision: u64,
+    changes: VecDeque<Change>,
+}
+
+impl ChangeLog {
+    pub(crate) fn record(&mut self, change: Change) {
+        self.revision += 1;
+
+        if self.changes.len() == CHANGE_LOG_CAPACITY {
+            self.changes.pop_front();
+        }
+
+        self.changes.push_back(change);
+    }
+
+    /// Changes made after a revision, or `None` if some of them are not remembered anymore.
+    fn since(&self, revision: u64) -> Option<impl Iterator<Item = &Change>> {
+        let count = usize::try_from(self.revision.checked_sub(revision)?).ok()?;
+
+        (count <= self.changes.len()).then(|| self.changes.iter().skip(self.changes.len() - count))
+    }
+}
+
+/// Counters of a [`PathCache`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PathCacheStats {
+    /// Queries answered from the cache.
+    pub hits: u64,
+    /// Queries that had to search.
+    pub misses: u64,
+    /// Paths evicted because the octree changed.
+    pub invalidations: u64,
+}
+
+impl PathCacheStats {
+    /// Ratio of queries answered from the cache, zero if there were no queries.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn hit_rate(&self) -> f32 {
+        let queries = self.hits + self.misses;
+
+        if queries == 0 {
+            return 0.0;
+        }
+
+        self.hits as f32 / queries as f32
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CachedPath {
+    path: Path,
+    last_used: u64,
+}
+
+/// Cache of paths between pairs of links of a single octree.
+///
+/// Before answering a query, the cache catches up with the changes of the octree made since
+/// the last query and evicts every path going through a link touched by an incremental update,
+/// a blocker toggle, a cost volume or an off-mesh link. Only complete paths are cached. When
+/// the cache is full, the least recently used path is evicted.
+///
+/// Changes that only open new space, such as removed voxels, evict only the paths going
+/// through the changed links, so the remaining paths stay valid but may not be the cheapest
+/// anymore.
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{PathCache, PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+/// use bevy_math::{IVec3, Vec3};
+///
+/// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+///
+/// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+/// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+///
+/// let mut octree = builder.build();
+/// let mut cache = PathCache::new(PathOptions::default(), 64);
+///
+/// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+/// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+///
+/// let path = cache.find_path(&octree, start, goal).unwrap();
+/// assert_eq!(cache.find_path(&octree, start, goal), Some(path.clone()));
+///
+/// // Filling a voxel on the path evicts it.
+/// let middle = octree.node_position(path.links[path.links.len() / 2]);
+/// octree.set_voxel(middle, true);
+///
+/// assert!(cache.find_path(&octree, start, goal).is_some());
+/// assert_eq!(cache.stats().invalidations, 1);
+/// assert_eq!(cache.stats().hit_rate(), 1.0 / 3.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct PathCache {
+    options: PathOptions,
+    capacity: usize,
+    /// Revision of the octree the cache caught up with.
+    revision: u64,
+    entries: HashMap<(SparseVoxelOctreeLink, SparseVoxelOctreeLink), CachedPath>,
+    /// Keys of the cached paths going through a link.
+    paths_through:
+        HashMap<SparseVoxelOctreeLink, HashSet<(SparseVoxelOctreeLink, SparseVoxelOctreeLink)>>,
+    /// Number of queries, used to find the least recently used path.
+    clock: u64,
+    stats: PathCacheStats,
+}
+
+impl PathCache {
+    /// Creates an empty cache of at most `capacity` paths searched with `options`.
+    #[must_use]
+    pub fn new(options: PathOptions, capacity: usize) -> Self {
+        Self {
+            options,
+            capacity,
+            revision: 0,
+            entries: HashMap::new(),
+            paths_through: HashMap::new(),
+            clock: 0,
+            stats: PathCacheStats::default(),
+        }
+    }
+
+    /// Returns the cached path between two links, or searches for it with
+    /// [`SparseVoxelOctree::find_path`] and caches it.
+    ///
+    /// The cache must always be used with the same octree.
+    pub fn find_path(
+        &mut self,
+        octree: &SparseVoxelOctree,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+    ) -> Option<Path> {
+        self.sync(octree);
+        self.clock += 1;
+
+        if let Some(entry) = self.entries.get_mut(&(start, goal)) {
+            entry.last_used = self.clock;
+            self.stats.hits += 1;
+
+            return Some(entry.path.clone());
+        }
+
+        self.stats.misses += 1;
+
+        let path = octree.find_path(start, goal, &self.options)?;
+
+        if self.capacity > 0 && path.links.last() == Some(&goal) {
+            if self.entries.len() >= self.capacity {
+                self.evict_least_recently_used();
+            }
+
+            self.insert((start, goal), path.clone());
+        }
+
+        Some(path)
+    }
+
+    /// Removes all cached paths. The statistics are kept.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.paths_through.clear();
+    }
+
+    /// Number of cached paths.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns true if no paths are cached.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hits, misses and invalidations since the cache was created.
+    #[must_use]
+    pub fn stats(&self) -> PathCacheStats {
+        self.stats
+    }
+
+    /// Evicts the paths touched by the changes of the octree since the last query.
+    fn sync(&mut self, octree: &SparseVoxelOctree) {
+        if self.revision == octree.revision() {
+            return;
+        }
+
+        let previous = std::mem::replace(&mut self.revision, octree.revision());
+
+        let Some(changes) = octree.change_log.since(previous) else {
+            self.stats.invalidations += self.entries.len() as u64;
+            self.clear();
+            return;
+        };
+
+        let mut touched = HashSet::new();
+
+        for change in changes {
+            match change {
+                Change::Links(links) => {
+                    for link in links {
+                        if let Some(keys) = self.paths_through.get(link) {
+                            touched.extend(keys.iter().copied());
+                        }
+                    }
+                }
+                Change::Volume(shape) => {
+                    touched.extend(
+                        self.entries
+                            .iter()
+                            .filter(|(_, entry)| {
+                                entry.path.links.iter().any(|link| {
+                                    let (min, max) = octree.link_bounds(*link);
+
+                                    shape.intersects_box(min, max)
+                                })
+                            })
+                            .map(|(key, _)| *key),
+                    );
+                }
+            }
+        }
+
+        for key in touched {
+            if self.remove(key) {
+                self.stats.invalidations += 1;
+            }
+        }
+    }
+
+    fn insert(&mut self, key: (SparseVoxelOctreeLink, SparseVoxelOctreeLink), path: Path) {
+        for link in &path.links {
+            self.paths_through.entry(*link).or_default().insert(key);
+        }
+
+        self.entries.insert(
+            key,
+            CachedPath {
+                path,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: (SparseVoxelOctreeLink, SparseVoxelOctreeLink)) -> bool {
+        let Some(entry) = self.entries.remove(&key) else {
+            return false;
+        };
+
+        for link in &entry.path.links {
+            if let Some(keys) = self.paths_through.get_mut(link) {
+                keys.remove(&key);
+
+                if keys.is_empty() {
+                    self.paths_through.remove(link);
+                }
+            }
+        }
+
+        true
+    }
+
+    fn evict_least_recently_used(&mut self) {
+        let oldest = self
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.last_used)
+            .map(|(key, _)| *key);
+
+        if let Some(key) = oldest {
+            self.remove(key);
+        }
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Number of changes made to the octree since it was built.
+    ///
+    /// Every incremental update, blocker toggle, cost volume or off-mesh link that may change
+    /// paths increments the revision.
+    #[must_use]
+    pub fn revision(&self) -> u64 {
+        self.change_log.revision
+    }
+
+    pub(crate) fn record_change(&mut self, change: Change) {
+        self.change_log.record(change);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, Vec3};
+
+    use crate::{OffMeshLink, SparseVoxelOctreeBuilder, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_path_cache_evicts_touched_paths() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::sphere(3.0, 1.0, IVec3::ZERO));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        let blocker = builder.add_blocker(
+            VolumeShape::Box {
+                min: Vec3::new(5.0, 5.0, 5.0),
+                max: Vec3::new(6.0, 6.0, 6.0),
+            },
+            false,
+        );
+
+        let mut tree = builder.build();
+        let mut cache = PathCache::new(PathOptions::default(), 8);
+
+        assert_eq!(tree.revision(), 0);
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+        let inside = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        let path = cache.find_path(&tree, start, goal).unwrap();
+
+        assert_eq!(cache.find_path(&tree, start, goal).as_ref(), Some(&path));
+        assert!(cache.find_path(&tree, start, inside).is_none());
+        assert_eq!(cache.len(), 1);
+
+        // Changes away from the path keep it.
+        let far = [
+            Vec3::new(7.5, 7.5, 7.5),
+            Vec3::new(-7.5, -7.5, -7.5),
+            Vec3::new(7.5, -7.5, 7.5),
+            Vec3::new(-7.5, 7.5, -7.5),
+        ]
+        .into_iter()
+        .find(|position| !path.links.contains(&tree.find_node(*position).unwrap()))
+        .unwrap();
+
+        tree.set_voxel(far, true).unwrap();
+
+        assert_eq!(cache.find_path(&tree, start, goal).as_ref(), Some(&path));
+
+        // Filling a voxel on the path evicts it.
+        let middle = tree.node_position(path.links[path.links.len() / 2]);
+        tree.set_voxel(middle, true).unwrap();
+
+        let repaired = cache.find_path(&tree, start, goal).unwrap();
+
+        assert_ne!(repaired.links, path.links);
+        assert_eq!(
+            cache.stats(),
+            PathCacheStats {
+                hits: 2,
+                misses: 3,
+                invalidations: 1,
+            }
+        );
+
+        // Cost volumes and off-mesh links touching the path evict it as well.
+        let volume = tree.add_cost_volume(
+            VolumeShape::Sphere {
+                center: tree.node_position(repaired.links[1]),
+                radius: 0.5,
+            },
+            10.0,
+        );
+
+        cache.find_path(&tree, start, goal).unwrap();
+        assert_eq!(cache.stats().invalidations, 2);
+
+        assert!(tree.remove_cost_volume(volume));
+        assert!(!tree.remove_cost_volume(volume));
+
+        // The detour around the volume doesn't overlap it, so it stays cached.
+        let path = cache.find_path(&tree, start, goal).unwrap();
+        assert_eq!(cache.stats().invalidations, 2);
+
+        tree.add_off_mesh_link(OffMeshLink {
+            start: Vec3::new(-6.5, 0.5, 0.5),
+            end: Vec3::new(6.5, 0.5, 0.5),
+            bidirectional: false,
+            cost: 1.0,
+            user_id: 0,
+        });
+
+        let shortcut = cache.find_path(&tree, start, goal).unwrap();
+
+        assert_eq!(shortcut.links, vec![start, goal]);
+        assert_ne!(shortcut.links, path.links);
+        assert_eq!(cache.stats().invalidations, 3);
+
+        // Toggling a blocker the path doesn't go through keeps it.
+        let revision = tree.revision();
+
+        tree.set_blocker_enabled(blocker, true).unwrap();
+
+        assert_eq!(tree.revision(), revision + 1);
+        assert_eq!(cache.find_path(&tree, start, goal), Some(shortcut));
+        assert_eq!(cache.stats().invalidations, 3);
+
+        // Falling behind the change log clears the cache.
+        for _ in 0..=CHANGE_LOG_CAPACITY {
+            tree.set_blocker_enabled(blocker, false).unwrap();
+            tree.set_blocker_enabled(blocker, true).unwrap();
+        }
+
+        cache.find_path(&tree, start, goal).unwrap();
+
+        assert_eq!(cache.stats().invalidations, 4);
+        assert_eq!(cache.len(), 1);
+
+        // The least recently used path is evicted when the cache is full.
+        let mut small = PathCache::new(PathOptions::default(), 1);
+
+        small.find_path(&tree, start, goal).unwrap();
+        small.find_path(&tree, goal, start).unwrap();
+
+        assert_eq!(small.len(), 1);
+        assert!(small.find_path(&tree, goal, start).is_some());
+        assert!((small.stats().hit_rate() - 1.0 / 3.0).abs() < f32::EPSILON);
+    }
+}
@@ -0,0 +1,743 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use bevy_math::Vec3;
+
+use crate::{OffMeshLinkId, QueryFilter, SparseVoxelOctree, SparseVoxelOctreeLink, VoxelState};
+
+/// Describes how a planner treats space that was never observed.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum UnknownSpace {
+    /// Unknown space is traversed the same way as free space.
+    #[default]
+    Free,
+    /// Unknown space is never traversed.
+    Blocked,
+    /// Entering unknown space is more expensive. The cost of the edge is multiplied by the value,
+    /// which is never lower than 1.
+    Cost(f32),
+}
+
+/// Options of the built-in path search.
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{PathOptions, UnknownSpace};
+///
+/// let options = PathOptions {
+///     unknown_space: UnknownSpace::Blocked,
+///     ..Default::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct PathOptions {
+    /// How unknown space is treated.
+    pub unknown_space: UnknownSpace,
+    /// Area types that can be entered and their costs.
+    pub filter: QueryFilter,
+    /// If true and the goal can't be reached, the path to the reached link closest to the goal
+    /// is returned instead of `None`.
+    pub allow_partial: bool,
+}
+
+/// Path found by the built-in path search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Path {
+    /// Links from the start to the goal, both included.
+    pub links: Vec<SparseVoxelOctreeLink>,
+    /// Sum of the costs of all edges of the path in world units.
+    pub cost: f32,
+    /// Off-mesh links used by the path together with the index of the link in `links` where
+    /// they end.
+    pub off_mesh_links: Vec<(usize, OffMeshLinkId)>,
+    /// True if the goal was unreachable and the path ends in the reached link closest to it
+    /// instead.
+    pub is_partial: bool,
+}
+
+impl SparseVoxelOctree {
+    /// Finds the cheapest path between two links using A*.
+    ///
+    /// The cost of an edge is the distance between the centers of the two nodes in world space,
+    /// multiplied by the area cost and the cost volumes of the entered node and adjusted by
+    /// `options`. Returns `None` if there is no path, unless partial paths are allowed by
+    /// [`PathOptions::allow_partial`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let path = octree.find_path(start, goal, &PathOptions::default()).unwrap();
+    ///
+    /// assert_eq!(path.links.first(), Some(&start));
+    /// assert_eq!(path.links.last(), Some(&goal));
+    /// ```
+    #[must_use]
+    pub fn find_path(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: &PathOptions,
+    ) -> Option<Path> {
+        self.find_path_within(start, goal, options, |_| true)
+    }
+
+    /// Finds the cheapest path from a link to the closest of a set of goal positions.
+    ///
+    /// All goals are searched at once using the distance to the nearest goal as the heuristic,
+    /// which is cheaper than searching for each goal separately. Goals outside of the octree
+    /// are ignored. Returns the index of the reached goal together with the path, or `None` if
+    /// no goal can be reached. Partial paths end in the reached link closest to any goal.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let pads = [Vec3::new(6.5, -6.5, -6.5), Vec3::new(-6.5, -6.5, 0.5)];
+    ///
+    /// let (index, path) = octree
+    ///     .find_path_to_any(start, &pads, &PathOptions::default())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(index, 1);
+    /// assert_eq!(path.links.last(), octree.find_node(pads[1]).as_ref());
+    /// ```
+    #[must_use]
+    pub fn find_path_to_any(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goals: &[Vec3],
+        options: &PathOptions,
+    ) -> Option<(usize, Path)> {
+        let goals = goals
+            .iter()
+            .enumerate()
+            .filter_map(|(index, position)| Some((self.find_node(*position)?, index)))
+            .collect::<Vec<_>>();
+
+        if goals.is_empty() {
+            return None;
+        }
+
+        let path = self.search(
+            start,
+            options,
+            |_| true,
+            |link| goals.iter().any(|(goal, _)| *goal == link),
+            |link| {
+                goals
+                    .iter()
+                    .map(|(goal, _)| self.heuristic(link, *goal))
+                    .fold(f32::INFINITY, f32::min)
+            },
+        )?;
+
+        let last = *path.links.last()?;
+
+        // Partial paths report the goal they got closest to.
+        let (_, index) = goals
+            .iter()
+            .min_by(|a, b| {
+                self.heuristic(last, a.0)
+                    .total_cmp(&self.heuristic(last, b.0))
+            })
+            .copied()?;
+
+        Some((index, path))
+    }
+
+    /// Finds the cheapest path from a link to any link for which `is_goal` returns true.
+    ///
+    /// Without a position of the goal there is no heuristic, so the search expands links in the
+    /// order of their cost like Dijkstra's algorithm. [`PathOptions::allow_partial`] is ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let path = octree
+    ///     .find_path_matching(start, |link| octree.node_position(link).x > 0.0, &PathOptions::default())
+    ///     .unwrap();
+    ///
+    /// assert!(octree.node_position(*path.links.last().unwrap()).x > 0.0);
+    /// ```
+    #[must_use]
+    pub fn find_path_matching(
+        &self,
+        start: SparseVoxelOctreeLink,
+        is_goal: impl Fn(SparseVoxelOctreeLink) -> bool,
+        options: &PathOptions,
+    ) -> Option<Path> {
+        let options = PathOptions {
+            allow_partial: false,
+            ..options.clone()
+        };
+
+        self.search(start, &options, |_| true, is_goal, |_| 0.0)
+    }
+
+    /// Same as [`SparseVoxelOctree::find_path`] but only enters links for which `is_allowed`
+    /// returns true.
+    pub(crate) fn find_path_within(
+        &self,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: &PathOptions,
+        is_allowed: impl Fn(SparseVoxelOctreeLink) -> bool,
+    ) -> Option<Path> {
+        self.search(
+            start,
+            options,
+            is_allowed,
+            |link| link == goal,
+            |link| self.heuristic(link, goal),
+        )
+    }
+
+    /// A* search ending in the first expanded link for which `is_goal` returns true.
+    fn search(
+        &self,
+        start: SparseVoxelOctreeLink,
+        options: &PathOptions,
+        is_allowed: impl Fn(SparseVoxelOctreeLink) -> bool,
+        is_goal: impl Fn(SparseVoxelOctreeLink) -> bool,
+        heuristic: impl Fn(SparseVoxelOctreeLink) -> f32,
+    ) -> Option<Path> {
+        self.search_with(
+            &mut SearchScratch::default(),
+            start,
+            options,
+            is_allowed,
+            is_goal,
+            heuristic,
+        )
+    }
+
+    /// Same as [`SparseVoxelOctree::search`] but reuses the allocations of `scratch`.
+    pub(crate) fn search_with(
+        &self,
+        scratch: &mut SearchScratch,
+        start: SparseVoxelOctreeLink,
+        options: &PathOptions,
+        is_allowed: impl Fn(SparseVoxelOctreeLink) -> bool,
+        is_goal: impl Fn(SparseVoxelOctreeLink) -> bool,
+        heuristic: impl Fn(SparseVoxelOctreeLink) -> f32,
+    ) -> Option<Path> {
+        scratch.start(start, heuristic(start));
+
+        loop {
+            match scratch.expand(self, options, &is_allowed, &is_goal, &heuristic) {
+                SearchStep::Expanded => {}
+                SearchStep::Found(path) => return Some(path),
+                SearchStep::Exhausted => {
+                    return options
+                        .allow_partial
+                        .then(|| scratch.partial_path(self, &is_goal));
+                }
+            }
+        }
+    }
+
+    /// Creates a path from its links and finds the off-mesh links it uses.
+    pub(crate) fn path(&self, links: Vec<SparseVoxelOctreeLink>, cost: f32) -> Path {
+        let off_mesh_links = links
+            .windows(2)
+            .enumerate()
+            .filter_map(|(index, pair)| {
+                self.off_mesh_link_between(pair[0], pair[1])
+                    .map(|(id, _)| (index + 1, id))
+            })
+            .collect();
+
+        Path {
+            links,
+            cost,
+            off_mesh_links,
+            is_partial: false,
+        }
+    }
+
+    /// Cost of moving between two neighboring links or `None` if `to` can't be entered.
+    ///
+    /// When an off-mesh link leads from `from` to `to`, its cost is used instead of the
+    /// distance.
+    pub(crate) fn edge_cost(
+        &self,
+        from: SparseVoxelOctreeLink,
+        to: SparseVoxelOctreeLink,
+        options: &PathOptions,
+    ) -> Option<f32> {
+        let area = self.area(to);
+
+        if !options.filter.passes(area) {
+            return None;
+        }
+
+        let distance = self.off_mesh_link_between(from, to).map_or_else(
+            || self.node_position(from).distance(self.node_position(to)),
+            |(_, cost)| cost,
+        ) * options.filter.area_cost(area)
+            * self.cost_multiplier(to);
+
+        match (self.state(to), options.unknown_space) {
+            (VoxelState::Occupied, _) | (VoxelState::Unknown, UnknownSpace::Blocked) => None,
+            (VoxelState::Unknown, UnknownSpace::Cost(multiplier)) => {
+                Some(distance * multiplier.max(1.0))
+            }
+            _ => Some(distance),
+        }
+    }
+
+    /// Links that may have an edge leading into a link.
+    pub(crate) fn predecessors(&self, link: SparseVoxelOctreeLink) -> Vec<SparseVoxelOctreeLink> {
+        if !self.is_cell(link) {
+            return Vec::new();
+        }
+
+        // Face neighbors are symmetric, only off-mesh links need to be looked up in reverse.
+        let mut result = self.successors(link);
+        result.extend(self.off_mesh_links.sources(link));
+
+        result
+    }
+
+    /// Admissible estimate of the cost between two links.
+    ///
+    /// The distance between the nodes is scaled down when off-mesh links are cheaper than the
+    /// distance they cover.
+    #[inline]
+    pub(crate) fn heuristic(&self, from: SparseVoxelOctreeLink, to: SparseVoxelOctreeLink) -> f32 {
+        self.node_position(from).distance(self.node_position(to))
+            * self.off_mesh_links.heuristic_scale()
+    }
+}
+
+/// Walks the parents from `goal` back to the start.
+pub(crate) fn reconstruct_path(
+    parents: &HashMap<SparseVoxelOctreeLink, SparseVoxelOctreeLink>,
+    goal: SparseVoxelOctreeLink,
+) -> Vec<SparseVoxelOctreeLink> {
+    let mut links = vec![goal];
+    let mut current = goal;
+
+    while let Some(parent) = parents.get(&current) {
+        links.push(*parent);
+        current = *parent;
+    }
+
+    links.reverse();
+    links
+}
+
+/// Allocations of a search that can be reused by following searches.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SearchScratch {
+    open: BinaryHeap<OpenNode>,
+    costs: HashMap<SparseVoxelOctreeLink, f32>,
+    parents: HashMap<SparseVoxelOctreeLink, SparseVoxelOctreeLink>,
+    /// Reached link with the lowest heuristic.
+    closest: Option<(SparseVoxelOctreeLink, f32)>,
+}
+
+/// Result of [`SearchScratch::expand`].
+pub(crate) enum SearchStep {
+    /// A link was expanded or an outdated entry of the open set was skipped.
+    Expanded,
+    /// A goal was reached.
+    Found(Path),
+    /// All reachable links were expanded without reaching a goal.
+    Exhausted,
+}
+
+impl SearchScratch {
+    /// Removes all entries but keeps the allocated memory.
+    fn clear(&mut self) {
+        self.open.clear();
+        self.costs.clear();
+        self.parents.clear();
+        self.closest = None;
+    }
+
+    /// Clears the scratch and opens the start of a new search.
+    pub(crate) fn start(&mut self, start: SparseVoxelOctreeLink, heuristic: f32) {
+        self.clear();
+        self.closest = Some((start, heuristic));
+        self.costs.insert(start, 0.0);
+        self.open.push(OpenNode {
+            estimate: heuristic,
+            cost: 0.0,
+            link: start,
+        });
+    }
+
+    /// Pops the cheapest link of the open set and opens its successors. This is a single
+    /// iteration of the A* search of [`SparseVoxelOctree::search_with`].
+    pub(crate) fn expand(
+        &mut self,
+        octree: &SparseVoxelOctree,
+        options: &PathOptions,
+        is_allowed: impl Fn(SparseVoxelOctreeLink) -> bool,
+        is_goal: impl Fn(SparseVoxelOctreeLink) -> bool,
+        heuristic: impl Fn(SparseVoxelOctreeLink) -> f32,
+    ) -> SearchStep {
+        let Some(OpenNode { cost, link, .. }) = self.open.pop() else {
+            return SearchStep::Exhausted;
+        };
+
+        if is_goal(link) {
+            return SearchStep::Found(octree.path(reconstruct_path(&self.parents, link), cost));
+        }
+
+        if self.costs.get(&link).is_some_and(|best| cost > *best) {
+            return SearchStep::Expanded;
+        }
+
+        for successor in octree.successors(link) {
+            if !is_allowed(successor) {
+                continue;
+            }
+
+            let Some(edge_cost) = octree.edge_cost(link, successor, options) else {
+                continue;
+            };
+
+            let successor_cost = cost + edge_cost;
+
+            if self
+                .costs
+                .get(&successor)
+                .is_some_and(|best| successor_cost >= *best)
+            {
+                continue;
+            }
+
+            let heuristic = heuristic(successor);
+
+            if self
+                .closest
+                .map_or(true, |(_, closest)| heuristic < closest)
+            {
+                self.closest = Some((successor, heuristic));
+            }
+
+            self.costs.insert(successor, successor_cost);
+            self.parents.insert(successor, link);
+            self.open.push(OpenNode {
+                estimate: successor_cost + heuristic,
+                cost: successor_cost,
+                link: successor,
+            });
+        }
+
+        SearchStep::Expanded
+    }
+
+    /// Path to the reached link with the lowest heuristic, partial unless it is a goal.
+    pub(crate) fn partial_path(
+        &self,
+        octree: &SparseVoxelOctree,
+        is_goal: impl Fn(SparseVoxelOctreeLink) -> bool,
+    ) -> Path {
+        let (closest, _) = self
+            .closest
+            .expect("search was started before taking its path");
+
+        let mut path = octree.path(
+            reconstruct_path(&self.parents, closest),
+            self.costs[&closest],
+        );
+        path.is_partial = !is_goal(closest);
+
+        path
+    }
+
+    /// Number of links reached so far.
+    pub(crate) fn reached_count(&self) -> usize {
+        self.costs.len()
+    }
+}
+
+/// Entry of the open set ordered by the lowest estimated total cost first.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct OpenNode {
+    pub(crate) estimate: f32,
+    pub(crate) cost: f32,
+    pub(crate) link: SparseVoxelOctreeLink,
+}
+
+impl PartialEq for OpenNode {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenNode {}
+
+impl PartialOrd for OpenNode {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenNode {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the binary heap pops the lowest estimate first. Ties prefer deeper nodes.
+        other
+            .estimate
+            .total_cmp(&self.estimate)
+            .then_with(|| self.cost.total_cmp(&other.cost))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3, Vec3};
+
+    use crate::{
+        test_fixtures::sphere, AreaType, SparseVoxelOctreeBuilder, VoxelState, VoxelizedMesh,
+    };
+
+    use super::*;
+
+    fn wall() -> Vec<UVec3> {
+        let mut voxels = Vec::new();
+
+        for y in 0..12 {
+            for z in 0..12 {
+                voxels.push(UVec3::new(0, y, z));
+            }
+        }
+
+        voxels
+    }
+
+    #[test]
+    fn test_find_path_around_wall() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(wall(), 1.0, IVec3::new(0, -6, -6)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let tree = builder.build();
+
+        let start = tree.find_node(Vec3::new(-2.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(2.5, 0.5, 0.5)).unwrap();
+
+        let path = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        assert_eq!(path.links[0], start);
+        assert_eq!(*path.links.last().unwrap(), goal);
+        assert!(path.cost > 12.0);
+
+        for link in &path.links {
+            assert_ne!(tree.state(*link), VoxelState::Occupied);
+        }
+
+        for pair in path.links.windows(2) {
+            assert!(tree.successors(pair[0]).contains(&pair[1]));
+        }
+    }
+
+    #[test]
+    fn test_find_path_through_unknown_space() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        builder.add_mesh_with_state(
+            VoxelizedMesh::new(wall(), 1.0, IVec3::new(0, -6, -6)),
+            VoxelState::Unknown,
+        );
+
+        let tree = builder.build();
+
+        let start = tree.find_node(Vec3::new(-2.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(2.5, 0.5, 0.5)).unwrap();
+
+        let free = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        let blocked = tree
+            .find_path(
+                start,
+                goal,
+                &PathOptions {
+                    unknown_space: UnknownSpace::Blocked,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        let expensive = tree
+            .find_path(
+                start,
+                goal,
+                &PathOptions {
+                    unknown_space: UnknownSpace::Cost(100.0),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+
+        assert!(free.cost < blocked.cost);
+        assert!((blocked.cost - expensive.cost).abs() < 1e-4);
+        assert!(blocked
+            .links
+            .iter()
+            .all(|link| tree.state(*link) != VoxelState::Unknown));
+    }
+
+    #[test]
+    fn test_partial_path_to_unreachable_goal() {
+        let tree = sphere(3.0);
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        assert_eq!(tree.state(goal), VoxelState::Occupied);
+        assert!(tree
+            .find_path(start, goal, &PathOptions::default())
+            .is_none());
+
+        let options = PathOptions {
+            allow_partial: true,
+            ..Default::default()
+        };
+
+        let partial = tree.find_path(start, goal, &options).unwrap();
+        let closest = *partial.links.last().unwrap();
+
+        assert!(partial.is_partial);
+        assert_ne!(closest, goal);
+        assert!(tree.heuristic(closest, goal) < 4.0);
+
+        // Snapping the goal to the closest free node makes it reachable.
+        let snapped = tree.nearest_free_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        assert_ne!(tree.state(snapped), VoxelState::Occupied);
+        assert!(tree.heuristic(snapped, goal) < 4.0);
+
+        let path = tree.find_path(start, snapped, &options).unwrap();
+
+        assert!(!path.is_partial);
+        assert_eq!(path.links.last(), Some(&snapped));
+    }
+
+    #[test]
+    fn test_find_path_to_nearest_goal() {
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(wall(), 1.0, IVec3::new(0, -6, -6)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let tree = builder.build();
+
+        let start = tree.find_node(Vec3::new(-2.5, 0.5, 0.5)).unwrap();
+
+        // The first goal is closer in a straight line, but hidden behind the wall.
+        let goals = [
+            Vec3::new(2.5, 0.5, 0.5),
+            Vec3::new(-2.5, 0.5, 7.5),
+            Vec3::new(100.0, 0.0, 0.0),
+        ];
+
+        let (index, path) = tree
+            .find_path_to_any(start, &goals, &PathOptions::default())
+            .unwrap();
+
+        let costs = goals[..2]
+            .iter()
+            .map(|goal| {
+                tree.find_path(
+                    start,
+                    tree.find_node(*goal).unwrap(),
+                    &PathOptions::default(),
+                )
+                .unwrap()
+                .cost
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(index, 1);
+        assert!(costs[1] < costs[0]);
+        assert!((path.cost - costs[1]).abs() < 1e-4);
+        assert_eq!(path.links.last(), tree.find_node(goals[1]).as_ref());
+
+        let hidden = tree.find_node(goals[0]).unwrap();
+        let matching = tree
+            .find_path_matching(start, |link| link == hidden, &PathOptions::default())
+            .unwrap();
+
+        assert!((matching.cost - costs[0]).abs() < 1e-4);
+        assert!(tree
+            .find_path_to_any(start, &goals[2..], &PathOptions::default())
+            .is_none());
+    }
+
+    #[test]
+    fn test_find_path_with_query_filter() {
+        let danger = AreaType::new(2);
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        builder.add_mesh_with_state(
+            VoxelizedMesh::new(wall(), 1.0, IVec3::new(0, -6, -6)).with_area(danger),
+            VoxelState::Free,
+        );
+
+        let tree = builder.build();
+
+        let start = tree.find_node(Vec3::new(-2.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(2.5, 0.5, 0.5)).unwrap();
+
+        let through = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        let mut options = PathOptions::default();
+        options.filter.exclude_flags = danger.flag();
+
+        let around = tree.find_path(start, goal, &options).unwrap();
+
+        options.filter.exclude_flags = 0;
+        options.filter.set_area_cost(danger, 100.0);
+
+        let expensive = tree.find_path(start, goal, &options).unwrap();
+
+        assert!(through.links.iter().any(|link| tree.area(*link) == danger));
+        assert!(around.links.iter().all(|link| tree.area(*link) != danger));
+        assert!(through.cost < around.cost);
+        assert!((around.cost - expensive.cost).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,313 @@
+use bevy_math::Vec3;
+
+use crate::{Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Number of links before and after a blocked segment replaced by a local repair.
+const REPAIR_WINDOW: usize = 4;
+
+/// Distance in voxels a local repair may leave the bounds of the replaced links.
+const REPAIR_MARGIN_IN_VOXELS: f32 = 4.0;
+
+/// Result of [`SparseVoxelOctree::validate_path`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathValidity {
+    /// Every segment of the path can still be traversed.
+    Valid,
+    /// The segment from the link at `index` to the next link is the first one that can't be
+    /// traversed anymore.
+    Blocked { index: usize },
+}
+
+impl SparseVoxelOctree {
+    /// Checks if a path found earlier can still be traversed after the octree changed.
+    ///
+    /// A segment is blocked when one of its links was subdivided, when the next link is not
+    /// returned by [`SparseVoxelOctree::successors`] of the previous one anymore, for example
+    /// because it is covered by a blocker or an off-mesh link was removed, or when it can't be
+    /// entered with `options`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, PathValidity, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let options = PathOptions::default();
+    /// let path = octree.find_path(start, goal, &options).unwrap();
+    ///
+    /// assert_eq!(octree.validate_path(&path.links, &options), PathValidity::Valid);
+    ///
+    /// octree.set_voxel(Vec3::new(6.5, 0.5, 0.5), true);
+    ///
+    /// assert!(matches!(
+    ///     octree.validate_path(&path.links, &options),
+    ///     PathValidity::Blocked { .. }
+    /// ));
+    /// ```
+    #[must_use]
+    pub fn validate_path(
+        &self,
+        links: &[SparseVoxelOctreeLink],
+        options: &PathOptions,
+    ) -> PathValidity {
+        links
+            .windows(2)
+            .position(|pair| !self.is_segment_passable(pair[0], pair[1], options))
+            .map_or(PathValidity::Valid, |index| PathValidity::Blocked { index })
+    }
+
+    /// Repairs the part of a path starting at `from_index`, usually the link the agent is in.
+    ///
+    /// Every blocked segment is replaced by a path searched only in the surroundings of a few
+    /// links before and after it. When such a detour doesn't exist, the whole path is searched
+    /// again with [`SparseVoxelOctree::find_path`]. If the start or the goal was subdivided or
+    /// merged since the path was found, it is replaced by the cell containing its center. The
+    /// returned path starts at the link at `from_index` and its cost is updated. Returns `None`
+    /// if `from_index` is out of the path or the goal can't be reached anymore.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{PathOptions, PathValidity, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    ///
+    /// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+    /// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+    ///
+    /// let options = PathOptions::default();
+    /// let path = octree.find_path(start, goal, &options).unwrap();
+    ///
+    /// let middle = octree.node_position(path.links[path.links.len() / 2]);
+    /// octree.set_voxel(middle, true);
+    ///
+    /// let repaired = octree.repair_path(&path, 0, &options).unwrap();
+    ///
+    /// assert_eq!(octree.validate_path(&repaired.links, &options), PathValidity::Valid);
+    /// assert_eq!(repaired.links.last(), Some(&goal));
+    /// ```
+    #[must_use]
+    pub fn repair_path(
+        &self,
+        path: &Path,
+        from_index: usize,
+        options: &PathOptions,
+    ) -> Option<Path> {
+        let mut links = path.links.get(from_index..)?.to_vec();
+        let start = self.cell_at_center(*links.first()?)?;
+        let goal = self.cell_at_center(*links.last()?)?;
+
+        links[0] = start;
+        *links.last_mut()? = goal;
+
+        // Segments before this index are known to be passable.
+        let mut checked = 0;
+
+        while let PathValidity::Blocked { index } = self.validate_path(&links[checked..], options) {
+            let index = checked + index;
+
+            let Some((from, to, detour)) = self.local_detour(&links, index, options) else {
+                return self.find_path(start, goal, options);
+            };
+
+            checked = from + detour.len() - 1;
+            links.splice(from..=to, detour);
+        }
+
+        let cost = links
+            .windows(2)
+            .map(|pair| self.edge_cost(pair[0], pair[1], options))
+            .sum::<Option<f32>>()?;
+
+        Some(self.path(links, cost))
+    }
+
+    /// Cell containing the center of a link, which is the link itself unless it was subdivided
+    /// or merged.
+    pub(crate) fn cell_at_center(
+        &self,
+        link: SparseVoxelOctreeLink,
+    ) -> Option<SparseVoxelOctreeLink> {
+        if self.is_cell(link) {
+            return Some(link);
+        }
+
+        self.find_node(self.node_position(link))
+    }
+
+    /// Returns true if an agent can move from one link to the next one of a path.
+    fn is_segment_passable(
+        &self,
+        from: SparseVoxelOctreeLink,
+        to: SparseVoxelOctreeLink,
+        options: &PathOptions,
+    ) -> bool {
+        self.is_cell(from)
+            && self.is_cell(to)
+            && self.successors(from).contains(&to)
+            && self.edge_cost(from, to, options).is_some()
+    }
+
+    /// Searches for a path around the blocked segment starting at `index` that rejoins the
+    /// original path. Returns the range of replaced links, both included, and the links
+    /// replacing them.
+    fn local_detour(
+        &self,
+        links: &[SparseVoxelOctreeLink],
+        index: usize,
+        options: &PathOptions,
+    ) -> Option<(usize, usize, Vec<SparseVoxelOctreeLink>)> {
+        // The start of the path is always a cell.
+        let from = (0..=index.saturating_sub(REPAIR_WINDOW))
+            .rev()
+            .find(|from| self.is_cell(links[*from]))?;
+        let to = (index + 1 + REPAIR_WINDOW..links.len())
+            .chain(std::iter::once(links.len() - 1))
+            .find(|to| self.is_cell(links[*to]))?;
+
+        let (mut min, mut max) = (Vec3::splat(f32::INFINITY), Vec3::splat(f32::NEG_INFINITY));
+
+        for link in &links[from..=to] {
+            let (link_min, link_max) = self.link_bounds(*link);
+
+            min = min.min(link_min);
+            max = max.max(link_max);
+        }
+
+        let margin = Vec3::splat(REPAIR_MARGIN_IN_VOXELS * self.voxel_size);
+        let (min, max) = (min - margin, max + margin);
+
+        let options = PathOptions {
+            allow_partial: false,
+            ..options.clone()
+        };
+
+        let detour = self.find_path_within(links[from], links[to], &options, |link| {
+            let (link_min, link_max) = self.link_bounds(link);
+
+            link_min.cmplt(max).all() && link_max.cmpgt(min).all()
+        })?;
+
+        Some((from, to, detour.links))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::{test_fixtures::sphere, VolumeShape};
+
+    use super::*;
+
+    #[test]
+    fn test_repair_blocked_path() {
+        let mut tree = sphere(3.0);
+        let options = PathOptions::default();
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+
+        let path = tree.find_path(start, goal, &options).unwrap();
+
+        assert_eq!(
+            tree.validate_path(&path.links, &options),
+            PathValidity::Valid
+        );
+        assert_eq!(tree.validate_path(&[], &options), PathValidity::Valid);
+        assert_eq!(tree.repair_path(&path, 0, &options).as_ref(), Some(&path));
+        assert!(tree
+            .repair_path(&path, path.links.len(), &options)
+            .is_none());
+
+        // Repairing from the middle of the path keeps the rest of it.
+        let rest = tree.repair_path(&path, 2, &options).unwrap();
+
+        assert_eq!(rest.links, path.links[2..]);
+
+        // A blocker across the middle of the path blocks the first segment entering it.
+        let middle = path.links.len() / 2;
+        let (min, max) = tree.link_bounds(path.links[middle]);
+
+        tree.add_blocker(VolumeShape::Box { min, max }, true);
+
+        let PathValidity::Blocked { index } = tree.validate_path(&path.links, &options) else {
+            panic!("path through the blocker is valid");
+        };
+
+        assert!(index <= middle);
+
+        let repaired = tree.repair_path(&path, 0, &options).unwrap();
+
+        assert_eq!(
+            tree.validate_path(&repaired.links, &options),
+            PathValidity::Valid
+        );
+        assert_eq!(repaired.links.first(), Some(&start));
+        assert_eq!(repaired.links.last(), Some(&goal));
+        assert!(!repaired.links.iter().any(|link| tree.is_blocked(*link)));
+
+        // The start of the path is kept as it was.
+        assert_eq!(
+            repaired.links[..index.saturating_sub(REPAIR_WINDOW)],
+            path.links[..index.saturating_sub(REPAIR_WINDOW)]
+        );
+
+        // An unreachable goal can't be repaired.
+        tree.set_voxel(tree.node_position(goal), true).unwrap();
+
+        assert!(tree.repair_path(&repaired, 0, &options).is_none());
+    }
+
+    #[test]
+    fn test_repair_path_with_subdivided_ends() {
+        let mut tree = sphere(3.0);
+        let options = PathOptions::default();
+
+        let start = tree.find_node(Vec3::new(-6.5, -6.5, -6.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, 6.5, 6.5)).unwrap();
+
+        let path = tree.find_path(start, goal, &options).unwrap();
+
+        for link in [start, goal] {
+            let (min, max) = tree.link_bounds(link);
+            let center = tree.node_position(link);
+
+            assert!((max - min).x > 1.0, "end of the path isn't a large node");
+
+            // Fill a voxel in a corner of the node, away from its center.
+            tree.set_voxel(min + Vec3::splat(0.5), true).unwrap();
+
+            assert!(!tree.is_cell(link));
+
+            let repaired = tree.repair_path(&path, 0, &options).unwrap();
+            let end = if link == start {
+                repaired.links.first()
+            } else {
+                repaired.links.last()
+            };
+
+            assert_eq!(end, tree.find_node(center).as_ref());
+            assert_eq!(
+                tree.validate_path(&repaired.links, &options),
+                PathValidity::Valid
+            );
+        }
+    }
+}
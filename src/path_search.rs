@@ -0,0 +1,205 @@
+use crate::{
+    path_finding::{SearchScratch, SearchStep},
+    Path, PathOptions, SparseVoxelOctree, SparseVoxelOctreeLink,
+};
+
+/// State of a [`PathSearch`] after a step.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathSearchStatus {
+    /// The search ran out of expansions and has to be stepped again.
+    InProgress,
+    /// The cheapest path was found.
+    Found(Path),
+    /// There is no path between the links.
+    NoPath,
+}
+
+/// A* search that can be spread over multiple frames.
+///
+/// The search keeps its own open and closed sets and pops at most the given number of links
+/// per [`PathSearch::step`]. It doesn't borrow the octree, the octree passed to the steps must
+/// not change while the search is in progress.
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{PathOptions, PathSearch, PathSearchStatus, SparseVoxelOctreeBuilder, VoxelizedMesh};
+/// use bevy_math::{IVec3, Vec3};
+///
+/// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+///
+/// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+/// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+///
+/// let octree = builder.build();
+///
+/// let start = octree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+/// let goal = octree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+///
+/// let mut search = PathSearch::new(&octree, start, goal, PathOptions::default());
+///
+/// let path = loop {
+///     match search.step(&octree, 4) {
+///         PathSearchStatus::InProgress => continue,
+///         PathSearchStatus::Found(path) => break path,
+///         PathSearchStatus::NoPath => panic!("no path"),
+///     }
+/// };
+///
+/// assert_eq!(path.links.last(), Some(&goal));
+/// ```
+#[derive(Debug, Clone)]
+pub struct PathSearch {
+    goal: SparseVoxelOctreeLink,
+    options: PathOptions,
+    scratch: SearchScratch,
+    status: PathSearchStatus,
+}
+
+impl PathSearch {
+    /// Starts a search between two links. No links are expanded until
+    /// [`PathSearch::step`] is called.
+    #[must_use]
+    pub fn new(
+        octree: &SparseVoxelOctree,
+        start: SparseVoxelOctreeLink,
+        goal: SparseVoxelOctreeLink,
+        options: PathOptions,
+    ) -> Self {
+        let mut scratch = SearchScratch::default();
+        scratch.start(start, octree.heuristic(start, goal));
+
+        Self {
+            goal,
+            options,
+            scratch,
+            status: PathSearchStatus::InProgress,
+        }
+    }
+
+    /// Pops at most `max_expansions` links from the open set and returns the state of the
+    /// search.
+    ///
+    /// The search is the same as [`SparseVoxelOctree::find_path`], so if the goal can't be
+    /// reached and [`PathOptions::allow_partial`] is set, the partial path is returned as found.
+    /// Once the search finished, further steps return the same result without any work.
+    pub fn step(&mut self, octree: &SparseVoxelOctree, max_expansions: usize) -> PathSearchStatus {
+        if self.status != PathSearchStatus::InProgress {
+            return self.status.clone();
+        }
+
+        let goal = self.goal;
+
+        for _ in 0..max_expansions {
+            let step = self.scratch.expand(
+                octree,
+                &self.options,
+                |_| true,
+                |link| link == goal,
+                |link| octree.heuristic(link, goal),
+            );
+
+            self.status = match step {
+                SearchStep::Expanded => continue,
+                SearchStep::Found(path) => PathSearchStatus::Found(path),
+                SearchStep::Exhausted if self.options.allow_partial => {
+                    PathSearchStatus::Found(self.partial_path(octree))
+                }
+                SearchStep::Exhausted => PathSearchStatus::NoPath,
+            };
+
+            return self.status.clone();
+        }
+
+        PathSearchStatus::InProgress
+    }
+
+    /// Path from the start to the reached link closest to the goal.
+    ///
+    /// Agents can start moving along it before the search finishes.
+    #[must_use]
+    pub fn partial_path(&self, octree: &SparseVoxelOctree) -> Path {
+        self.scratch.partial_path(octree, |link| link == self.goal)
+    }
+
+    /// Number of links reached so far.
+    #[must_use]
+    pub fn reached_count(&self) -> usize {
+        self.scratch.reached_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::Vec3;
+
+    use crate::test_fixtures::sphere;
+
+    use super::*;
+
+    #[test]
+    fn test_time_sliced_search_matches_find_path() {
+        let tree = sphere(3.0);
+
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(6.5, 0.5, 0.5)).unwrap();
+
+        let expected = tree
+            .find_path(start, goal, &PathOptions::default())
+            .unwrap();
+
+        let mut search = PathSearch::new(&tree, start, goal, PathOptions::default());
+
+        assert_eq!(search.partial_path(&tree).links, vec![start]);
+        assert_eq!(search.step(&tree, 1), PathSearchStatus::InProgress);
+
+        let partial = search.partial_path(&tree);
+
+        assert_eq!(partial.links.first(), Some(&start));
+        assert!(tree.heuristic(*partial.links.last().unwrap(), goal) < tree.heuristic(start, goal));
+
+        let mut steps = 1;
+
+        let path = loop {
+            steps += 1;
+
+            match search.step(&tree, 1) {
+                PathSearchStatus::InProgress => {}
+                PathSearchStatus::Found(path) => break path,
+                PathSearchStatus::NoPath => panic!("no path"),
+            }
+        };
+
+        assert!(steps > 2);
+        assert_eq!(path, expected);
+        assert_eq!(search.step(&tree, 1), PathSearchStatus::Found(path));
+    }
+
+    #[test]
+    fn test_time_sliced_search_to_unreachable_goal() {
+        let tree = sphere(3.0);
+
+        // Goal inside of the sphere.
+        let start = tree.find_node(Vec3::new(-6.5, 0.5, 0.5)).unwrap();
+        let goal = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+
+        let mut search = PathSearch::new(&tree, start, goal, PathOptions::default());
+
+        assert_eq!(search.step(&tree, usize::MAX), PathSearchStatus::NoPath);
+        assert!(search.reached_count() > 1);
+
+        // Partial paths are returned the same way as by `find_path`.
+        let options = PathOptions {
+            allow_partial: true,
+            ..Default::default()
+        };
+        let mut search = PathSearch::new(&tree, start, goal, options.clone());
+        let partial = tree.find_path(start, goal, &options).unwrap();
+
+        assert!(partial.is_partial);
+        assert_eq!(
+            search.step(&tree, usize::MAX),
+            PathSearchStatus::Found(partial)
+        );
+    }
+}
@@ -0,0 +1,105 @@
+use crate::{AreaType, SparseVoxelOctree, SparseVoxelOctreeLink};
+
+/// Per-agent filter of the area types that can be entered and of their costs.
+///
+/// An area type can be entered if it is in `include_flags` and not in `exclude_flags`. The
+/// flags are bit masks where the bit `n` belongs to the area type with id `n`, see
+/// [`AreaType::flag`].
+///
+/// # Example
+///
+/// ```
+/// use svo_rs::{AreaType, QueryFilter};
+///
+/// const WATER: AreaType = AreaType::new(1);
+/// const DANGER: AreaType = AreaType::new(2);
+///
+/// let mut submarine = QueryFilter::default();
+/// submarine.include_flags = WATER.flag();
+///
+/// let mut gunship = QueryFilter::default();
+/// gunship.set_area_cost(DANGER, 5.0);
+///
+/// assert!(!submarine.passes(AreaType::DEFAULT));
+/// assert!(submarine.passes(WATER));
+/// assert_eq!(gunship.area_cost(DANGER), 5.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryFilter {
+    /// Area types that can be entered.
+    pub include_flags: u64,
+    /// Area types that can't be entered, even if they are included.
+    pub exclude_flags: u64,
+    costs: [f32; AreaType::COUNT as usize],
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self {
+            include_flags: u64::MAX,
+            exclude_flags: 0,
+            costs: [1.0; AreaType::COUNT as usize],
+        }
+    }
+}
+
+impl QueryFilter {
+    /// Returns true if the area type can be entered.
+    #[must_use]
+    #[inline]
+    pub fn passes(&self, area: AreaType) -> bool {
+        self.include_flags & area.flag() != 0 && self.exclude_flags & area.flag() == 0
+    }
+
+    /// Sets the multiplier of the cost of entering an area type.
+    ///
+    /// Multipliers lower than 1 are raised to 1, so the distance stays an admissible heuristic.
+    pub fn set_area_cost(&mut self, area: AreaType, multiplier: f32) {
+        self.costs[area.id() as usize] = multiplier.max(1.0);
+    }
+
+    /// Returns the multiplier of the cost of entering an area type.
+    #[must_use]
+    #[inline]
+    pub fn area_cost(&self, area: AreaType) -> f32 {
+        self.costs[area.id() as usize]
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Same as [`SparseVoxelOctree::successors`], but only returns links whose area type
+    /// passes the filter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{AreaType, QueryFilter, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    /// builder.add_area_volume(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 0.0, 8.0), AreaType::new(1));
+    ///
+    /// let octree = builder.build();
+    /// let link = octree.find_node(Vec3::new(-4.0, -4.0, -4.0)).unwrap();
+    ///
+    /// let mut filter = QueryFilter::default();
+    /// filter.exclude_flags = AreaType::new(1).flag();
+    ///
+    /// assert_eq!(octree.successors(link).len(), 3);
+    /// assert_eq!(octree.filtered_successors(link, &filter).len(), 1);
+    /// ```
+    #[must_use]
+    pub fn filtered_successors(
+        &self,
+        link: SparseVoxelOctreeLink,
+        filter: &QueryFilter,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        let mut successors = self.successors(link);
+
+        successors.retain(|successor| filter.passes(self.area(*successor)));
+
+        successors
+    }
+}
@@ -3,15 +3,22 @@
 use bevy_math::{IVec3, UVec3, Vec3};
 
 use crate::{
+    blocker::Blockers,
     cohen_sutherland::{cohen_sutherland, LineClippingResult},
     compound_node::CompoundNode,
     consts::{
         NEIGHBOR_CONNECTIONS, NEIGHBOR_POSITION_OFFSETS, NEIGHBOR_SUBNODES,
-        OFFSETS_IN_MORTON_CODE_ORDER, SUBNODE_NEIGHBORS, SUBNODE_POSITIONS,
+        OFFSETS_IN_MORTON_CODE_ORDER, OPPOSITE_FACES, SIBLING_CONNECTIONS, SUBNODE_NEIGHBORS,
+        SUBNODE_POSITIONS,
     },
+    cost_volume::CostVolumes,
     morton_code::MortonCode,
+    off_mesh_link::OffMeshLinks,
+    path_cache::{Change, ChangeLog},
     sparse_voxel_octree_link::SparseVoxelOctreeLink,
     sparse_voxel_octree_node::SparseVoxelOctreeNode,
+    voxel_state::VoxelState,
+    AreaType,
 };
 
 /// Implementation of a sparse voxel octree.
@@ -36,6 +43,9 @@ use crate::{
 /// Each node has a position in space, parent, children and a list of neighbors to allow
 /// for easy navigation through the tree.
 ///
+/// The octree is `Send` and `Sync`, so it can be queried from many threads at once, for
+/// example with [`SparseVoxelOctree::find_paths`].
+///
 /// # Example
 ///
 /// ```
@@ -73,13 +83,44 @@ pub struct SparseVoxelOctree {
     /// It has the same ordering as the layer[0] so you can use the same index
     /// to access both.
     pub(crate) leafs: Vec<CompoundNode>,
+
+    /// Compound nodes marking voxels that were never observed.
+    ///
+    /// It has the same ordering as `leafs`. A voxel is never both filled and unknown.
+    pub(crate) unknown: Vec<CompoundNode>,
+
+    /// Area types of single voxels of leaf nodes whose voxels have different areas.
+    ///
+    /// It has the same ordering as `leafs`. `None` means that all voxels of the leaf have the
+    /// area of the leaf node.
+    pub(crate) areas: Vec<Option<Box<[AreaType; 64]>>>,
+
+    /// Volumes changing the cost of entering nodes without changing the nodes.
+    pub(crate) cost_volumes: CostVolumes,
+
+    /// User defined connections between nodes that don't share a face.
+    pub(crate) off_mesh_links: OffMeshLinks,
+
+    /// Toggleable shapes making the nodes they cover impassable.
+    pub(crate) blockers: Blockers,
+
+    /// Latest changes of the octree, used to invalidate cached paths.
+    pub(crate) change_log: ChangeLog,
+}
+
+/// Result of updating an air node by [`SparseVoxelOctree::update_box`].
+enum BoxUpdate {
+    Unchanged,
+    Changed,
+    Subdivide,
 }
 
 impl SparseVoxelOctree {
     /// Retrieves all neighbors of a node.
     ///
     /// Takes into account also if the neighboring node is subdivided or a leaf node,
-    /// returning the children is available.
+    /// returning the children is available. Ends of off-mesh links starting in the node are
+    /// returned as well.
     ///
     /// # Arguments
     ///
@@ -106,30 +147,47 @@ impl SparseVoxelOctree {
     ///
     /// let neighbors = octree.successors(link);
     ///
-    /// assert_eq!(neighbors.len(), 3);
+    /// assert_eq!(neighbors.len(), 6);
     /// ```
     #[must_use]
     pub fn successors(&self, link: SparseVoxelOctreeLink) -> Vec<SparseVoxelOctreeLink> {
+        let mut result = self.face_neighbors(link);
+
+        result.extend(self.off_mesh_links.targets(link));
+        result.retain(|successor| !self.is_blocked(*successor));
+
+        result
+    }
+
+    /// Neighbors sharing a face with a node, including the ones covered by blockers.
+    pub(crate) fn face_neighbors(&self, link: SparseVoxelOctreeLink) -> Vec<SparseVoxelOctreeLink> {
         let mut result = Vec::with_capacity(16);
 
         let node = &self.layers[link.layer_index][link.node_index];
 
         for i in 0..6 {
+            if let Some(subnode) = link.subnode_index {
+                // Subnodes inside of the same leaf are connected even if the leaf has no neighbor.
+                if !CompoundNode::is_face(subnode, i) {
+                    let neighbor_index = SUBNODE_NEIGHBORS[subnode as usize][i];
+                    let leaf_node = &self.leafs[link.node_index];
+                    if !leaf_node.get_by_index(neighbor_index) {
+                        result.push(SparseVoxelOctreeLink::new(
+                            link.layer_index,
+                            link.node_index,
+                            Some(neighbor_index),
+                        ));
+                    }
+
+                    continue;
+                }
+            }
+
             if let Some(neighbor) = &node.neighbors[i] {
                 let neighbor_node = &self.layers[neighbor.layer_index][neighbor.node_index];
 
                 if let Some(subnode) = link.subnode_index {
-                    if !CompoundNode::is_face(subnode, i) {
-                        let neighbor_index = SUBNODE_NEIGHBORS[subnode as usize][i];
-                        let leaf_node = &self.leafs[link.node_index];
-                        if !leaf_node.get_by_index(neighbor_index) {
-                            result.push(SparseVoxelOctreeLink::new(
-                                link.layer_index,
-                                link.node_index,
-                                Some(neighbor_index),
-                            ));
-                        }
-                    } else if neighbor_node.first_child.is_some() {
+                    if neighbor_node.first_child.is_some() {
                         result.append(&mut self.expand_to_neighboring_children(i, neighbor));
                     } else if neighbor_node.is_leaf {
                         if let Some(neighbor) =
@@ -161,7 +219,7 @@ impl SparseVoxelOctree {
     ) -> Option<SparseVoxelOctreeLink> {
         let leaf = &self.leafs[neighbor.node_index];
 
-        if leaf.is_empty() {
+        if self.is_uniform_leaf(neighbor.node_index) {
             return Some(*neighbor);
         }
 
@@ -190,7 +248,7 @@ impl SparseVoxelOctree {
     ) -> Vec<SparseVoxelOctreeLink> {
         let leaf = &self.leafs[neighbor.node_index];
 
-        if leaf.is_empty() {
+        if self.is_uniform_leaf(neighbor.node_index) {
             return vec![*neighbor];
         }
 
@@ -271,8 +329,7 @@ impl SparseVoxelOctree {
     /// ```
     #[must_use]
     pub fn find_node(&self, position: Vec3) -> Option<SparseVoxelOctreeLink> {
-        let voxel_position = (position / self.voxel_size).as_ivec3();
-        let voxel_position = (voxel_position - self.origin).as_uvec3();
+        let voxel_position = self.voxel_coordinates(position)?;
 
         let mut current_node = SparseVoxelOctreeLink::new(self.layers.len() - 1, 0, None);
 
@@ -280,8 +337,7 @@ impl SparseVoxelOctree {
             let node = &self.layers[current_node.layer_index][current_node.node_index];
 
             if node.is_leaf {
-                let leaf = &self.leafs[current_node.node_index];
-                if leaf.is_empty() {
+                if self.is_uniform_leaf(current_node.node_index) {
                     return Some(SparseVoxelOctreeLink::new(
                         current_node.layer_index,
                         current_node.node_index,
@@ -303,32 +359,955 @@ impl SparseVoxelOctree {
                 }
             }
 
-            if let Some(first_child) = node.first_child {
-                let mut found = false;
+            if node.first_child.is_some() {
+                match self.child_containing(current_node, voxel_position) {
+                    Some(child) => current_node = child,
+                    None => break,
+                }
+            } else {
+                return Some(current_node);
+            }
+        }
 
-                let offset = (voxel_position - node.position) / (node.size / 2);
+        None
+    }
 
-                for (i, item) in OFFSETS_IN_MORTON_CODE_ORDER.iter().enumerate() {
-                    if offset.x == Into::<u32>::into(item.0)
-                        && offset.y == Into::<u32>::into(item.1)
-                        && offset.z == Into::<u32>::into(item.2)
-                    {
-                        current_node = first_child;
-                        current_node.node_index += i;
-                        found = true;
-                        break;
+    /// Finds the node closest to a worldspace position that isn't occupied.
+    ///
+    /// Useful to snap goals that land inside of geometry. Returns the node containing the
+    /// position if it isn't occupied. Unknown space is not considered occupied. Returns `None`
+    /// if the position is outside of the octree or all voxels are occupied.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VoxelState, VoxelizedMesh};
+    /// use bevy_math::{IVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let link = octree.nearest_free_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+    ///
+    /// assert_ne!(octree.state(link), VoxelState::Occupied);
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn nearest_free_node(&self, position: Vec3) -> Option<SparseVoxelOctreeLink> {
+        let center = self.voxel_coordinates(position)?.as_ivec3();
+        let local_position = position / self.voxel_size - self.origin.as_vec3();
+        let size = self.size() as i32;
+
+        let mut closest: Option<(IVec3, f32)> = None;
+
+        for radius in 0..size {
+            // Voxels of the shell are at least this far from the position.
+            if closest.is_some_and(|(_, distance)| radius as f32 - 0.5 > distance) {
+                break;
+            }
+
+            for x in -radius..=radius {
+                for y in -radius..=radius {
+                    let on_side = x.abs() == radius || y.abs() == radius;
+                    let step = if on_side { 1 } else { (2 * radius).max(1) };
+
+                    for z in (-radius..=radius).step_by(step as usize) {
+                        let voxel = center + IVec3::new(x, y, z);
+
+                        if voxel.min_element() < 0 || voxel.max_element() >= size {
+                            continue;
+                        }
+
+                        if self.voxel_state_at(voxel.as_uvec3()) == VoxelState::Occupied {
+                            continue;
+                        }
+
+                        let distance = (voxel.as_vec3() + 0.5).distance(local_position);
+
+                        if closest.map_or(true, |(_, closest)| distance < closest) {
+                            closest = Some((voxel, distance));
+                        }
+                    }
+                }
+            }
+        }
+
+        let (voxel, _) = closest?;
+
+        self.find_node((voxel.as_vec3() + 0.5 + self.origin.as_vec3()) * self.voxel_size)
+    }
+
+    /// Distance from a worldspace position to the center of the nearest occupied voxel.
+    ///
+    /// Only voxels within `max_distance` are considered, `max_distance` is returned if there
+    /// are none. Returns `None` if the position is outside of the octree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::new(vec![UVec3::new(0, 0, 0)], 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// assert_eq!(octree.clearance(Vec3::new(3.5, 0.5, 0.5), 8.0), Some(3.0));
+    /// assert_eq!(octree.clearance(Vec3::new(-6.5, 0.5, 0.5), 2.0), Some(2.0));
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn clearance(&self, position: Vec3, max_distance: f32) -> Option<f32> {
+        let center = self.voxel_coordinates(position)?.as_ivec3();
+        let local_position = position / self.voxel_size - self.origin.as_vec3();
+        let size = self.size() as i32;
+        let max_radius = ((max_distance / self.voxel_size).ceil() as i32 + 1).min(size);
+
+        let mut closest = max_distance / self.voxel_size;
+
+        for radius in 0..max_radius {
+            // Voxels of the shell are at least this far from the position.
+            if radius as f32 - 0.5 > closest {
+                break;
+            }
+
+            for x in -radius..=radius {
+                for y in -radius..=radius {
+                    let on_side = x.abs() == radius || y.abs() == radius;
+                    let step = if on_side { 1 } else { (2 * radius).max(1) };
+
+                    for z in (-radius..=radius).step_by(step as usize) {
+                        let voxel = center + IVec3::new(x, y, z);
+
+                        if voxel.min_element() < 0 || voxel.max_element() >= size {
+                            continue;
+                        }
+
+                        if self.voxel_state_at(voxel.as_uvec3()) != VoxelState::Occupied {
+                            continue;
+                        }
+
+                        closest = closest.min((voxel.as_vec3() + 0.5).distance(local_position));
+                    }
+                }
+            }
+        }
+
+        Some((closest * self.voxel_size).min(max_distance))
+    }
+
+    /// Sets the voxel at a worldspace position to be either filled or empty.
+    ///
+    /// Air nodes covering the position are subdivided down to a leaf node when a voxel is filled.
+    /// New nodes are appended to the layers, so links obtained before the change stay valid.
+    ///
+    /// Returns links whose passability changed or `None` if the position is outside of the octree.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::new(vec![UVec3::new(0, 1, 0)], 1.0, IVec3::ZERO));
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let mut octree = builder.build();
+    ///
+    /// assert!(octree.is_in_line_of_sight(Vec3::new(-6.5, 0.5, 0.5), Vec3::new(-3.5, 0.5, 0.5)));
+    ///
+    /// octree.set_voxel(Vec3::new(-5.5, 0.5, 0.5), true);
+    ///
+    /// assert!(!octree.is_in_line_of_sight(Vec3::new(-6.5, 0.5, 0.5), Vec3::new(-3.5, 0.5, 0.5)));
+    /// ```
+    pub fn set_voxel(
+        &mut self,
+        position: Vec3,
+        occupied: bool,
+    ) -> Option<Vec<SparseVoxelOctreeLink>> {
+        let coordinates = self.voxel_coordinates(position)?;
+
+        Some(self.set_voxel_at(coordinates, occupied))
+    }
+
+    /// Same as [`SparseVoxelOctree::set_voxel`] but with voxel coordinates relative to the origin.
+    pub(crate) fn set_voxel_at(
+        &mut self,
+        coordinates: UVec3,
+        occupied: bool,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        let state = if occupied {
+            VoxelState::Occupied
+        } else {
+            VoxelState::Free
+        };
+
+        self.set_voxel_state_at(coordinates, state)
+    }
+
+    /// Sets the state of the voxel at a worldspace position.
+    ///
+    /// Works the same way as [`SparseVoxelOctree::set_voxel`], but allows marking voxels as
+    /// unknown.
+    ///
+    /// Returns links whose state changed or `None` if the position is outside of the octree.
+    pub fn set_voxel_state(
+        &mut self,
+        position: Vec3,
+        state: VoxelState,
+    ) -> Option<Vec<SparseVoxelOctreeLink>> {
+        let coordinates = self.voxel_coordinates(position)?;
+
+        Some(self.set_voxel_state_at(coordinates, state))
+    }
+
+    /// Same as [`SparseVoxelOctree::set_voxel_state`] but with voxel coordinates relative to the
+    /// origin.
+    pub(crate) fn set_voxel_state_at(
+        &mut self,
+        coordinates: UVec3,
+        state: VoxelState,
+    ) -> Vec<SparseVoxelOctreeLink> {
+        let mut changed = Vec::new();
+
+        self.apply_voxel_state(coordinates, state, &mut changed);
+        self.commit_changes(&changed);
+
+        changed
+    }
+
+    /// Same as [`SparseVoxelOctree::set_voxel_state_at`] but only adds the changed links to
+    /// `changed`. Batches of changes are committed at once with
+    /// [`SparseVoxelOctree::commit_changes`].
+    pub(crate) fn apply_voxel_state(
+        &mut self,
+        coordinates: UVec3,
+        state: VoxelState,
+        changed: &mut Vec<SparseVoxelOctreeLink>,
+    ) {
+        let Some(leaf) = self.leaf_containing(
+            coordinates,
+            |node| match state {
+                VoxelState::Free => node.is_unknown,
+                VoxelState::Occupied => true,
+                VoxelState::Unknown => !node.is_unknown,
+            },
+            changed,
+        ) else {
+            return;
+        };
+
+        let Ok(subnode) =
+            MortonCode::encode(coordinates - self.layers[0][leaf.node_index].position).as_u8()
+        else {
+            return;
+        };
+
+        let subnode_link = SparseVoxelOctreeLink::new(0, leaf.node_index, Some(subnode));
+
+        if self.set_subnode_state(subnode_link, state) {
+            changed.push(leaf);
+            changed.push(subnode_link);
+        }
+    }
+
+    /// Sets the state of all voxels in a box given by voxel coordinates relative to the origin.
+    /// `min` is inclusive and `max` exclusive.
+    ///
+    /// Air nodes that are fully inside of the box are changed as a whole, so large free or
+    /// unknown boxes don't need to be split down to single voxels.
+    ///
+    /// Adds links whose state changed and links of subdivided nodes to `changed`, which are
+    /// committed with [`SparseVoxelOctree::commit_changes`].
+    pub(crate) fn apply_box_state(
+        &mut self,
+        min: UVec3,
+        max: UVec3,
+        state: VoxelState,
+        changed: &mut Vec<SparseVoxelOctreeLink>,
+    ) {
+        self.update_box(
+            min,
+            max,
+            |octree, link, is_inside| {
+                if state == VoxelState::Occupied {
+                    BoxUpdate::Subdivide
+                } else if octree.state(link) == state {
+                    BoxUpdate::Unchanged
+                } else if is_inside {
+                    octree.layers[link.layer_index][link.node_index].is_unknown =
+                        state == VoxelState::Unknown;
+                    BoxUpdate::Changed
+                } else {
+                    BoxUpdate::Subdivide
+                }
+            },
+            |octree, link| octree.set_subnode_state(link, state),
+            changed,
+        );
+    }
+
+    /// Tags all voxels in a box given by voxel coordinates relative to the origin with an area
+    /// type. `min` is inclusive and `max` exclusive.
+    ///
+    /// Adds links whose area changed and links of subdivided nodes to `changed`, which are
+    /// committed with [`SparseVoxelOctree::commit_changes`].
+    pub(crate) fn apply_box_area(
+        &mut self,
+        min: UVec3,
+        max: UVec3,
+        area: AreaType,
+        changed: &mut Vec<SparseVoxelOctreeLink>,
+    ) {
+        self.update_box(
+            min,
+            max,
+            |octree, link, is_inside| {
+                let node = &mut octree.layers[link.layer_index][link.node_index];
+
+                if node.area == area {
+                    BoxUpdate::Unchanged
+                } else if is_inside {
+                    node.area = area;
+                    BoxUpdate::Changed
+                } else {
+                    BoxUpdate::Subdivide
+                }
+            },
+            |octree, link| octree.set_subnode_area(link, area),
+            changed,
+        );
+    }
+
+    /// Walks all nodes intersecting a box given by voxel coordinates relative to the origin.
+    ///
+    /// `update_node` is called for air nodes without children together with whether the node
+    /// is fully inside of the box. `update_voxel` is called for each voxel of leaf nodes inside
+    /// of the box and returns true if the voxel changed.
+    ///
+    /// Adds links of changed nodes and voxels and links of subdivided nodes to `changed`.
+    fn update_box(
+        &mut self,
+        min: UVec3,
+        max: UVec3,
+        mut update_node: impl FnMut(&mut Self, SparseVoxelOctreeLink, bool) -> BoxUpdate,
+        mut update_voxel: impl FnMut(&mut Self, SparseVoxelOctreeLink) -> bool,
+        changed: &mut Vec<SparseVoxelOctreeLink>,
+    ) {
+        let mut open = vec![SparseVoxelOctreeLink::new(self.layers.len() - 1, 0, None)];
+
+        while let Some(link) = open.pop() {
+            let node = &self.layers[link.layer_index][link.node_index];
+            let node_min = node.position;
+            let node_max = node.position + UVec3::splat(node.size);
+
+            if node_min.cmpge(max).any() || node_max.cmple(min).any() {
+                continue;
+            }
+
+            if node.is_leaf {
+                let mut leaf_changed = false;
+
+                for (subnode, offset) in (0u8..).zip(SUBNODE_POSITIONS.iter()) {
+                    let coordinates =
+                        node_min + UVec3::new(offset.0.into(), offset.1.into(), offset.2.into());
+
+                    if coordinates.cmplt(min).any() || coordinates.cmpge(max).any() {
+                        continue;
+                    }
+
+                    let subnode_link =
+                        SparseVoxelOctreeLink::new(0, link.node_index, Some(subnode));
+
+                    if update_voxel(self, subnode_link) {
+                        changed.push(subnode_link);
+                        leaf_changed = true;
+                    }
+                }
+
+                if leaf_changed {
+                    changed.push(link);
+                }
+
+                continue;
+            }
+
+            if node.first_child.is_none() {
+                let is_inside = node_min.cmpge(min).all() && node_max.cmple(max).all();
+
+                match update_node(self, link, is_inside) {
+                    BoxUpdate::Unchanged => continue,
+                    BoxUpdate::Changed => {
+                        changed.push(link);
+                        continue;
+                    }
+                    BoxUpdate::Subdivide => {
+                        self.subdivide(link);
+                        changed.push(link);
+                    }
+                }
+            }
+
+            if let Some(first_child) = self.layers[link.layer_index][link.node_index].first_child {
+                for child in 0..8 {
+                    open.push(SparseVoxelOctreeLink::new(
+                        first_child.layer_index,
+                        first_child.node_index + child,
+                        None,
+                    ));
+                }
+            }
+        }
+    }
+
+    /// Resolves off-mesh links and blockers once after a batch of changes and records the
+    /// changed links for [`crate::PathCache`].
+    pub(crate) fn commit_changes(&mut self, changed: &[SparseVoxelOctreeLink]) {
+        if changed.is_empty() {
+            return;
+        }
+
+        self.resolve_links(changed);
+        self.record_change(Change::Links(changed.to_vec()));
+    }
+
+    /// Sets the state of a single voxel of a leaf node. Returns true if the state changed.
+    fn set_subnode_state(&mut self, link: SparseVoxelOctreeLink, state: VoxelState) -> bool {
+        let Some(subnode) = link.subnode_index else {
+            return false;
+        };
+
+        if self.state(link) == state {
+            return false;
+        }
+
+        self.leafs[link.node_index].set_by_index(subnode, state == VoxelState::Occupied);
+        self.unknown[link.node_index].set_by_index(subnode, state == VoxelState::Unknown);
+
+        true
+    }
+
+    /// Sets the area type of a single voxel of a leaf node. Returns true if the area changed.
+    ///
+    /// Voxels of a leaf are stored separately only while they have different areas.
+    fn set_subnode_area(&mut self, link: SparseVoxelOctreeLink, area: AreaType) -> bool {
+        let Some(subnode) = link.subnode_index else {
+            return false;
+        };
+
+        if self.area(link) == area {
+            return false;
+        }
+
+        let node_area = self.layers[0][link.node_index].area;
+        let areas = self.areas[link.node_index].get_or_insert_with(|| Box::new([node_area; 64]));
+
+        areas[subnode as usize] = area;
+
+        if areas.iter().all(|voxel_area| *voxel_area == area) {
+            self.layers[0][link.node_index].area = area;
+            self.areas[link.node_index] = None;
+        }
+
+        true
+    }
+
+    /// Returns the area type of the space covered by a link.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{AreaType, SparseVoxelOctreeBuilder, VoxelizedMesh};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(
+    ///     VoxelizedMesh::new(vec![UVec3::new(0, 3, 0)], 1.0, IVec3::ZERO).with_area(AreaType::new(2)),
+    /// );
+    ///
+    /// let octree = builder.build();
+    /// let link = octree.find_node(Vec3::new(0.0, 3.0, 0.0)).unwrap();
+    ///
+    /// assert_eq!(octree.area(link), AreaType::new(2));
+    /// ```
+    #[must_use]
+    pub fn area(&self, link: SparseVoxelOctreeLink) -> AreaType {
+        if let Some(subnode) = link.subnode_index {
+            if let Some(areas) = &self.areas[link.node_index] {
+                return areas[subnode as usize];
+            }
+        }
+
+        self.layers[link.layer_index][link.node_index].area
+    }
+
+    /// Returns the area type of the voxel at a worldspace position or `None` if the position is
+    /// outside of the octree.
+    #[must_use]
+    pub fn voxel_area(&self, position: Vec3) -> Option<AreaType> {
+        let link = self.find_node(position)?;
+
+        Some(self.area(link))
+    }
+
+    /// Returns the state of the space covered by a link.
+    ///
+    /// Links to whole nodes are returned by [`SparseVoxelOctree::successors`] only for nodes
+    /// that are uniformly free or unknown.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VoxelizedMesh, VoxelState};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.add_mesh(VoxelizedMesh::new(vec![UVec3::new(0, 3, 0)], 1.0, IVec3::ZERO));
+    ///
+    /// let octree = builder.build();
+    /// let link = octree.find_node(Vec3::new(0.0, 3.0, 0.0)).unwrap();
+    ///
+    /// assert_eq!(octree.state(link), VoxelState::Occupied);
+    /// ```
+    #[must_use]
+    pub fn state(&self, link: SparseVoxelOctreeLink) -> VoxelState {
+        let node = &self.layers[link.layer_index][link.node_index];
+
+        if !node.is_leaf {
+            return if node.is_unknown {
+                VoxelState::Unknown
+            } else {
+                VoxelState::Free
+            };
+        }
+
+        let leaf = &self.leafs[link.node_index];
+        let unknown = &self.unknown[link.node_index];
+
+        let (is_occupied, is_unknown) = match link.subnode_index {
+            Some(subnode) => (leaf.get_by_index(subnode), unknown.get_by_index(subnode)),
+            None => (leaf.is_full(), leaf.is_empty() && !unknown.is_empty()),
+        };
+
+        if is_occupied {
+            VoxelState::Occupied
+        } else if is_unknown {
+            VoxelState::Unknown
+        } else {
+            VoxelState::Free
+        }
+    }
+
+    /// Returns the state of the voxel at a worldspace position or `None` if the position is
+    /// outside of the octree.
+    #[must_use]
+    pub fn voxel_state(&self, position: Vec3) -> Option<VoxelState> {
+        let link = self.find_node(position)?;
+
+        Some(self.state(link))
+    }
+
+    /// Returns the state of the voxel at coordinates relative to the origin.
+    pub(crate) fn voxel_state_at(&self, coordinates: UVec3) -> VoxelState {
+        let mut current_node = SparseVoxelOctreeLink::new(self.layers.len() - 1, 0, None);
+
+        loop {
+            let node = &self.layers[current_node.layer_index][current_node.node_index];
+
+            if node.is_leaf {
+                return match MortonCode::encode(coordinates - node.position).as_u8() {
+                    Ok(subnode) => self.state(SparseVoxelOctreeLink::new(
+                        current_node.layer_index,
+                        current_node.node_index,
+                        Some(subnode),
+                    )),
+                    Err(_) => self.state(current_node),
+                };
+            }
+
+            match self.child_containing(current_node, coordinates) {
+                Some(child) => current_node = child,
+                None => return self.state(current_node),
+            }
+        }
+    }
+
+    /// Returns true if a leaf node contains voxels of a single state other than filled, of a
+    /// single area type and not covered by a blocker.
+    /// Such leaf is navigated as a whole instead of through its subnodes.
+    #[inline]
+    pub(crate) fn is_uniform_leaf(&self, node_index: usize) -> bool {
+        let unknown = &self.unknown[node_index];
+
+        self.leafs[node_index].is_empty()
+            && (unknown.is_empty() || unknown.is_full())
+            && self.areas[node_index].is_none()
+            && !self.blockers.covers_leaf(node_index)
+    }
+
+    /// Returns all links to the smallest navigable parts of the octree, regardless of their state.
+    ///
+    /// These are nodes without children, uniform leaf nodes and subnodes of all other leaf nodes.
+    pub(crate) fn cells(&self) -> Vec<SparseVoxelOctreeLink> {
+        let mut result = Vec::new();
+
+        for (layer_index, layer) in self.layers.iter().enumerate() {
+            for (node_index, node) in layer.iter().enumerate() {
+                if node.first_child.is_some() {
+                    continue;
+                }
+
+                if node.is_leaf && !self.is_uniform_leaf(node_index) {
+                    for subnode in 0..64 {
+                        result.push(SparseVoxelOctreeLink::new(
+                            layer_index,
+                            node_index,
+                            Some(subnode),
+                        ));
                     }
+                } else {
+                    result.push(SparseVoxelOctreeLink::new(layer_index, node_index, None));
                 }
+            }
+        }
 
-                if !found {
-                    break;
+        result
+    }
+
+    /// Returns true if a link points to one of the smallest navigable parts of the octree, see
+    /// [`SparseVoxelOctree::cells`].
+    pub(crate) fn is_cell(&self, link: SparseVoxelOctreeLink) -> bool {
+        let Some(node) = self
+            .layers
+            .get(link.layer_index)
+            .and_then(|layer| layer.get(link.node_index))
+        else {
+            return false;
+        };
+
+        match link.subnode_index {
+            Some(_) => node.is_leaf && !self.is_uniform_leaf(link.node_index),
+            None => {
+                node.first_child.is_none()
+                    && (!node.is_leaf || self.is_uniform_leaf(link.node_index))
+            }
+        }
+    }
+
+    /// Returns links to all cells inside of the space covered by a link.
+    pub(crate) fn cells_within(&self, link: SparseVoxelOctreeLink) -> Vec<SparseVoxelOctreeLink> {
+        if link.subnode_index.is_some() {
+            return vec![link];
+        }
+
+        let mut result = Vec::new();
+        let mut open = vec![link];
+
+        while let Some(link) = open.pop() {
+            let node = &self.layers[link.layer_index][link.node_index];
+
+            if let Some(first_child) = node.first_child {
+                for child in 0..8 {
+                    open.push(SparseVoxelOctreeLink::new(
+                        first_child.layer_index,
+                        first_child.node_index + child,
+                        None,
+                    ));
+                }
+            } else if node.is_leaf && !self.is_uniform_leaf(link.node_index) {
+                for subnode in 0..64 {
+                    result.push(SparseVoxelOctreeLink::new(
+                        link.layer_index,
+                        link.node_index,
+                        Some(subnode),
+                    ));
                 }
             } else {
+                result.push(link);
+            }
+        }
+
+        result
+    }
+
+    /// Size of the space covered by a link in voxels along a single axis.
+    #[inline]
+    pub(crate) fn link_size(&self, link: SparseVoxelOctreeLink) -> u32 {
+        if link.subnode_index.is_some() {
+            1
+        } else {
+            self.layers[link.layer_index][link.node_index].size
+        }
+    }
+
+    /// Resolves off-mesh links and links covered by blockers after the octree changed.
+    ///
+    /// `changed` are the changed and subdivided links. Only blockers overlapping subdivided
+    /// nodes and off-mesh links with an end in a changed node are resolved again.
+    pub(crate) fn resolve_links(&mut self, changed: &[SparseVoxelOctreeLink]) {
+        let subdivided = changed
+            .iter()
+            .filter(|link| {
+                link.subnode_index.is_none()
+                    && self.layers[link.layer_index][link.node_index]
+                        .first_child
+                        .is_some()
+            })
+            .copied()
+            .collect::<Vec<_>>();
+
+        let covered = self.resolve_blockers_at(&subdivided);
+
+        self.resolve_off_mesh_links_at(changed);
+        self.resolve_off_mesh_links_at(&covered);
+    }
+
+    /// Minimal and maximal corner of the space covered by a link in world space.
+    pub(crate) fn link_bounds(&self, link: SparseVoxelOctreeLink) -> (Vec3, Vec3) {
+        #[allow(clippy::cast_precision_loss)]
+        let half_size = Vec3::splat(self.link_size(link) as f32 * self.voxel_size / 2.0);
+        let center = self.node_position(link);
+
+        (center - half_size, center + half_size)
+    }
+
+    /// Marks all space that is not filled as unknown.
+    pub(crate) fn mark_unknown(&mut self) {
+        for layer in &mut self.layers {
+            for node in layer.iter_mut() {
+                node.is_unknown = !node.is_leaf && node.first_child.is_none();
+            }
+        }
+
+        for (leaf, unknown) in self.leafs.iter().zip(self.unknown.iter_mut()) {
+            *unknown = CompoundNode::from_bits(!**leaf);
+        }
+    }
+
+    /// Size of the octree in voxels along a single axis.
+    pub(crate) fn size(&self) -> u32 {
+        self.layers
+            .last()
+            .and_then(|layer| layer.first())
+            .map_or(0, |root| root.size)
+    }
+
+    /// Converts a worldspace position to voxel coordinates relative to the origin of the octree.
+    ///
+    /// Returns `None` if the position is outside of the octree.
+    pub(crate) fn voxel_coordinates(&self, position: Vec3) -> Option<UVec3> {
+        let voxel_position = (position / self.voxel_size).floor().as_ivec3() - self.origin;
+
+        if voxel_position.min_element() < 0 {
+            return None;
+        }
+
+        let voxel_position = voxel_position.as_uvec3();
+
+        if voxel_position.max_element() >= self.size() {
+            return None;
+        }
+
+        Some(voxel_position)
+    }
+
+    /// Returns the child of a node that contains the voxel coordinates.
+    fn child_containing(
+        &self,
+        link: SparseVoxelOctreeLink,
+        coordinates: UVec3,
+    ) -> Option<SparseVoxelOctreeLink> {
+        let node = &self.layers[link.layer_index][link.node_index];
+        let first_child = node.first_child?;
+
+        if coordinates.cmplt(node.position).any() {
+            return None;
+        }
+
+        let offset = (coordinates - node.position) / (node.size / 2);
+
+        if offset.max_element() > 1 {
+            return None;
+        }
+
+        Some(SparseVoxelOctreeLink::new(
+            first_child.layer_index,
+            first_child.node_index + (offset.x + offset.y * 2 + offset.z * 4) as usize,
+            None,
+        ))
+    }
+
+    /// Walks from the root to the leaf node containing the voxel coordinates.
+    ///
+    /// Air nodes on the way for which `subdivide` returns true are split and links of the split
+    /// nodes are pushed to `changed`. Otherwise `None` is returned when the walk ends in an air
+    /// node.
+    fn leaf_containing(
+        &mut self,
+        coordinates: UVec3,
+        subdivide: impl Fn(&SparseVoxelOctreeNode) -> bool,
+        changed: &mut Vec<SparseVoxelOctreeLink>,
+    ) -> Option<SparseVoxelOctreeLink> {
+        let mut current_node = SparseVoxelOctreeLink::new(self.layers.len() - 1, 0, None);
+
+        loop {
+            let node = &self.layers[current_node.layer_index][current_node.node_index];
+
+            if node.is_leaf {
                 return Some(current_node);
             }
+
+            if node.first_child.is_none() {
+                if !subdivide(node) {
+                    return None;
+                }
+
+                self.subdivide(current_node);
+                changed.push(current_node);
+            }
+
+            current_node = self.child_containing(current_node, coordinates)?;
         }
+    }
 
-        None
+    /// Splits an air node into 8 children and connects them with their neighbors.
+    pub(crate) fn subdivide(&mut self, link: SparseVoxelOctreeLink) {
+        let node = &self.layers[link.layer_index][link.node_index];
+        let (position, size, neighbors, is_unknown, area) = (
+            node.position,
+            node.size,
+            node.neighbors,
+            node.is_unknown,
+            node.area,
+        );
+
+        let child_layer = link.layer_index - 1;
+        let first_child =
+            SparseVoxelOctreeLink::new(child_layer, self.layers[child_layer].len(), None);
+
+        for offset in &OFFSETS_IN_MORTON_CODE_ORDER {
+            let child_position = position
+                + UVec3::new(offset.0.into(), offset.1.into(), offset.2.into()) * (size / 2);
+
+            let mut child = if child_layer == 0 {
+                self.leafs.push(CompoundNode::new());
+                self.unknown.push(CompoundNode::from_bits(if is_unknown {
+                    u64::MAX
+                } else {
+                    0
+                }));
+                self.areas.push(None);
+                SparseVoxelOctreeNode::leaf(child_position)
+            } else {
+                let mut child = SparseVoxelOctreeNode::node(child_position, size / 2);
+                child.is_unknown = is_unknown;
+                child
+            };
+
+            child.parent = Some(link);
+            child.area = area;
+            self.layers[child_layer].push(child);
+        }
+
+        let node = &mut self.layers[link.layer_index][link.node_index];
+        node.first_child = Some(first_child);
+        node.is_unknown = false;
+
+        for (neighbor_index_1, neighbor_index_2, offset_1, offset_2) in &SIBLING_CONNECTIONS {
+            self.layers[child_layer][first_child.node_index + offset_1].neighbors
+                [*neighbor_index_1] = Some(SparseVoxelOctreeLink::new(
+                child_layer,
+                first_child.node_index + offset_2,
+                None,
+            ));
+
+            self.layers[child_layer][first_child.node_index + offset_2].neighbors
+                [*neighbor_index_2] = Some(SparseVoxelOctreeLink::new(
+                child_layer,
+                first_child.node_index + offset_1,
+                None,
+            ));
+        }
+
+        for (face, (own_children, neighbor_children)) in NEIGHBOR_CONNECTIONS.iter().enumerate() {
+            let Some(neighbor) = neighbors[face] else {
+                continue;
+            };
+
+            let neighbor_first_child = self.layers[neighbor.layer_index][neighbor.node_index]
+                .first_child
+                .filter(|_| neighbor.layer_index == link.layer_index);
+
+            for (own_child, neighbor_child) in own_children.iter().zip(neighbor_children) {
+                let child = SparseVoxelOctreeLink::new(
+                    child_layer,
+                    first_child.node_index + own_child,
+                    None,
+                );
+
+                if let Some(neighbor_first_child) = neighbor_first_child {
+                    let neighbor_child = SparseVoxelOctreeLink::new(
+                        neighbor_first_child.layer_index,
+                        neighbor_first_child.node_index + neighbor_child,
+                        None,
+                    );
+
+                    self.layers[child_layer][child.node_index].neighbors[face] =
+                        Some(neighbor_child);
+
+                    // The neighboring children and their descendants on this face were pointing
+                    // to the node that was just split.
+                    self.set_face_neighbor(neighbor_child, OPPOSITE_FACES[face], child);
+                } else {
+                    self.layers[child_layer][child.node_index].neighbors[face] = Some(neighbor);
+                }
+            }
+        }
+    }
+
+    /// Sets the neighbor of a node and all of its descendants lying on the given face.
+    fn set_face_neighbor(
+        &mut self,
+        link: SparseVoxelOctreeLink,
+        face: usize,
+        neighbor: SparseVoxelOctreeLink,
+    ) {
+        let mut open = vec![link];
+
+        while let Some(link) = open.pop() {
+            let node = &mut self.layers[link.layer_index][link.node_index];
+            node.neighbors[face] = Some(neighbor);
+
+            if let Some(first_child) = node.first_child {
+                for child in &NEIGHBOR_CONNECTIONS[face].0 {
+                    open.push(SparseVoxelOctreeLink::new(
+                        first_child.layer_index,
+                        first_child.node_index + child,
+                        None,
+                    ));
+                }
+            }
+        }
     }
 
     /// Draw lines between the node and all of its neighbors using bevy gizmos.
@@ -381,6 +1360,10 @@ impl SparseVoxelOctree {
             .max(IVec3::ZERO)
             .as_uvec3();
 
+        if self.is_segment_blocked(from.as_vec3(), to.as_vec3()) {
+            return false;
+        }
+
         let mut open = vec![SparseVoxelOctreeLink::new(self.layers.len() - 1, 0, None)];
 
         while let Some(link) = open.pop() {
@@ -945,4 +1928,78 @@ mod tests {
         let successor_20 = &tree.layers[successors[20].layer_index][successors[20].node_index];
         assert_eq!(successor_20.position, UVec3::new(4, 4, 4));
     }
+
+    #[test]
+    fn test_set_voxel_subdivides_air_node() {
+        let voxels = vec![UVec3::new(4, 4, 4), UVec3::new(80, 80, 80)];
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::ZERO));
+
+        let mut tree = builder.build();
+
+        let air_node = tree.find_node(Vec3::new(40.0, 40.0, 40.0)).unwrap();
+        assert_eq!(air_node.layer_index, 3);
+
+        let changed = tree.set_voxel(Vec3::new(40.5, 40.5, 40.5), true).unwrap();
+        assert!(changed.contains(&air_node));
+
+        let link = tree.find_node(Vec3::new(40.5, 40.5, 40.5)).unwrap();
+        assert_eq!(link.layer_index, 0);
+        assert!(tree.leafs[link.node_index].get_by_index(link.subnode_index.unwrap()));
+
+        let mut open = vec![tree.find_node(Vec3::new(6.5, 4.5, 4.5)).unwrap()];
+        let mut visited = std::collections::HashSet::new();
+
+        while let Some(link) = open.pop() {
+            if !visited.insert(link) {
+                continue;
+            }
+
+            for successor in tree.successors(link) {
+                assert!(
+                    tree.successors(successor).contains(&link),
+                    "{successor:?} is not connected back to {link:?}"
+                );
+                open.push(successor);
+            }
+        }
+
+        assert!(tree.set_voxel(Vec3::new(0.0, 0.0, 0.0), true).is_none());
+    }
+
+    #[test]
+    fn test_box_area_subdivides_and_collapses() {
+        let water = AreaType::new(1);
+
+        let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+        builder.add_area_volume(Vec3::ZERO, Vec3::new(2.0, 2.0, 2.0), water);
+
+        let mut tree = builder.build();
+
+        let tagged = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+        assert!(tagged.subnode_index.is_some());
+        assert_eq!(tree.area(tagged), water);
+        assert_eq!(
+            tree.voxel_area(Vec3::new(2.5, 0.5, 0.5)),
+            Some(AreaType::DEFAULT)
+        );
+        assert_eq!(
+            tree.voxel_area(Vec3::new(4.5, 0.5, 0.5)),
+            Some(AreaType::DEFAULT)
+        );
+
+        let leaf = tree.layers[0][tagged.node_index].position;
+        let mut changed = Vec::new();
+        tree.apply_box_area(leaf, leaf + UVec3::splat(4), water, &mut changed);
+        tree.commit_changes(&changed);
+
+        assert!(changed.contains(&SparseVoxelOctreeLink::new(0, tagged.node_index, None)));
+        assert!(tree.areas[tagged.node_index].is_none());
+
+        let link = tree.find_node(Vec3::new(0.5, 0.5, 0.5)).unwrap();
+        assert_eq!(link.subnode_index, None);
+        assert_eq!(tree.area(link), water);
+    }
 }
@@ -3,13 +3,18 @@ use std::collections::HashMap;
 use bevy_math::{IVec3, UVec3, Vec3};
 
 use crate::{
+    blocker::{BlockerId, Blockers},
     compound_node::CompoundNode,
     consts::{NEIGHBOR_CONNECTIONS, OFFSETS_IN_MORTON_CODE_ORDER, SIBLING_CONNECTIONS},
+    cost_volume::CostVolumes,
     morton_code::MortonCode,
+    off_mesh_link::OffMeshLinks,
+    path_cache::ChangeLog,
     sparse_voxel_octree_link::SparseVoxelOctreeLink,
     sparse_voxel_octree_node::SparseVoxelOctreeNode,
+    voxel_state::VoxelState,
     voxelized_mesh::VoxelizedMesh,
-    SparseVoxelOctree,
+    AreaType, SparseVoxelOctree, VolumeShape,
 };
 
 /// A builder for a sparse voxel octree.
@@ -31,6 +36,10 @@ use crate::{
 pub struct SparseVoxelOctreeBuilder {
     voxel_size: f32,
     meshes: Vec<VoxelizedMesh>,
+    state_meshes: Vec<(VoxelizedMesh, VoxelState)>,
+    area_volumes: Vec<(IVec3, IVec3, AreaType)>,
+    blockers: Vec<(VolumeShape, bool)>,
+    unknown_by_default: bool,
     min: IVec3,
     max: IVec3,
 }
@@ -51,6 +60,10 @@ impl SparseVoxelOctreeBuilder {
     pub fn new(voxel_size: f32) -> Self {
         Self {
             meshes: Vec::new(),
+            state_meshes: Vec::new(),
+            area_volumes: Vec::new(),
+            blockers: Vec::new(),
+            unknown_by_default: false,
             voxel_size,
             min: IVec3::MAX,
             max: IVec3::MIN,
@@ -73,6 +86,87 @@ impl SparseVoxelOctreeBuilder {
         self.meshes.push(mesh);
     }
 
+    /// Adds a voxelized mesh whose voxels are set to a specific state.
+    ///
+    /// Meshes added with `VoxelState::Occupied` behave the same as with
+    /// [`SparseVoxelOctreeBuilder::add_mesh`]. Free and unknown voxels are applied after the
+    /// octree is built and never override filled voxels.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, VoxelizedMesh, VoxelState};
+    /// use bevy_math::{IVec3, UVec3, Vec3};
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.set_unknown_by_default(true);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    /// builder.add_mesh_with_state(
+    ///     VoxelizedMesh::new(vec![UVec3::new(0, 0, 0)], 1.0, IVec3::ZERO),
+    ///     VoxelState::Free,
+    /// );
+    ///
+    /// let octree = builder.build();
+    ///
+    /// assert_eq!(octree.voxel_state(Vec3::new(0.5, 0.5, 0.5)), Some(VoxelState::Free));
+    /// assert_eq!(octree.voxel_state(Vec3::new(1.5, 0.5, 0.5)), Some(VoxelState::Unknown));
+    /// ```
+    pub fn add_mesh_with_state(&mut self, mesh: VoxelizedMesh, state: VoxelState) {
+        if state == VoxelState::Occupied {
+            self.meshes.push(mesh);
+        } else {
+            self.state_meshes.push((mesh, state));
+        }
+    }
+
+    /// Tags all space inside of a box with an area type. The box is specified in world space.
+    ///
+    /// Volumes are applied after the octree is built in the order they were added, before the
+    /// areas of meshes. Parts of the volume outside of the octree are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{AreaType, SparseVoxelOctreeBuilder};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    ///
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    /// builder.add_area_volume(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, -4.0, 8.0), AreaType::new(1));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// assert_eq!(octree.voxel_area(Vec3::new(0.5, -6.5, 0.5)), Some(AreaType::new(1)));
+    /// assert_eq!(octree.voxel_area(Vec3::new(0.5, 0.5, 0.5)), Some(AreaType::DEFAULT));
+    /// ```
+    pub fn add_area_volume(&mut self, min: Vec3, max: Vec3, area: AreaType) {
+        self.area_volumes.push((
+            (min / self.voxel_size).floor().as_ivec3(),
+            (max / self.voxel_size).ceil().as_ivec3(),
+            area,
+        ));
+    }
+
+    /// Registers a blocker that is added to the octree when it is built.
+    ///
+    /// Returns the id under which the blocker can be toggled, see
+    /// [`SparseVoxelOctree::add_blocker`].
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn add_blocker(&mut self, shape: VolumeShape, enabled: bool) -> BlockerId {
+        self.blockers.push((shape, enabled));
+
+        BlockerId(self.blockers.len() as u32 - 1)
+    }
+
+    /// Sets whether the space without any voxels is unknown instead of free.
+    ///
+    /// By default everything that is not voxelized is considered free.
+    pub fn set_unknown_by_default(&mut self, unknown_by_default: bool) {
+        self.unknown_by_default = unknown_by_default;
+    }
+
     /// Sets the minimal bounds of the octree Bounds are specified in world space.
     /// If some of the meshes are outside of the bounds, then the bounds will be expanded to include them.
     /// The final bounds of the octree will be also extended to be a power of two.
@@ -112,15 +206,38 @@ impl SparseVoxelOctreeBuilder {
     #[must_use]
     pub fn build(self) -> SparseVoxelOctree {
         let mut voxels = Vec::new();
+        let mut area_voxels = Vec::new();
 
         for mesh in self.meshes {
-            voxels.append(&mut mesh.voxels());
+            let mut mesh_voxels = mesh.voxels();
+
+            if mesh.area() != AreaType::DEFAULT {
+                area_voxels.extend(mesh_voxels.iter().map(|voxel| (*voxel, mesh.area())));
+            }
+
+            voxels.append(&mut mesh_voxels);
         }
 
+        let state_voxels = self
+            .state_meshes
+            .iter()
+            .flat_map(|(mesh, state)| {
+                mesh.voxels()
+                    .into_iter()
+                    .map(|voxel| (voxel, *state, mesh.area()))
+            })
+            .collect::<Vec<_>>();
+
         let (min, max) = Self::get_min_max(&voxels);
         let min = self.min.min(min);
         let max = self.max.max(max);
 
+        let (min, max) = state_voxels
+            .iter()
+            .fold((min, max), |(min, max), (voxel, _, _)| {
+                (min.min(*voxel), max.max(*voxel))
+            });
+
         let (origin, size) = Self::get_origin_and_size(min, max);
 
         let (layer_zero, leafs) = Self::collect_leafs_and_zero_layer_nodes(&voxels, origin);
@@ -140,12 +257,66 @@ impl SparseVoxelOctreeBuilder {
         Self::fill_parents(&mut layers);
         Self::fill_neighbors(&mut layers);
 
-        SparseVoxelOctree {
+        let unknown = leafs.iter().map(|_| CompoundNode::new()).collect();
+        let areas = leafs.iter().map(|_| None).collect();
+
+        let mut octree = SparseVoxelOctree {
             origin,
             layers,
             leafs,
+            unknown,
+            areas,
+            cost_volumes: CostVolumes::new(self.voxel_size),
+            off_mesh_links: OffMeshLinks::default(),
+            blockers: Blockers::default(),
+            change_log: ChangeLog::default(),
             voxel_size: self.voxel_size,
+        };
+
+        if self.unknown_by_default {
+            octree.mark_unknown();
         }
+
+        // Links are resolved once after all voxels and areas are applied.
+        let mut changed = Vec::new();
+
+        for (voxel, state, area) in state_voxels {
+            let coordinates = (voxel - origin).as_uvec3();
+
+            if octree.voxel_state_at(coordinates) != VoxelState::Occupied {
+                octree.apply_voxel_state(coordinates, state, &mut changed);
+
+                if area != AreaType::DEFAULT {
+                    area_voxels.push((voxel, area));
+                }
+            }
+        }
+
+        for (min, max, area) in self.area_volumes {
+            octree.apply_box_area(
+                (min - origin).max(IVec3::ZERO).as_uvec3(),
+                (max - origin).max(IVec3::ZERO).as_uvec3(),
+                area,
+                &mut changed,
+            );
+        }
+
+        for (voxel, area) in area_voxels {
+            let coordinates = (voxel - origin).as_uvec3();
+
+            octree.apply_box_area(coordinates, coordinates + UVec3::ONE, area, &mut changed);
+        }
+
+        octree.commit_changes(&changed);
+
+        for (shape, enabled) in self.blockers {
+            octree.add_blocker(shape, enabled);
+        }
+
+        // Changes made while building are part of the initial state.
+        octree.change_log = ChangeLog::default();
+
+        octree
     }
 
     /// Assigns parents to all nodes in the octree.
@@ -172,7 +343,7 @@ impl SparseVoxelOctreeBuilder {
             return;
         }
 
-        if layers[0].is_empty() {
+        if layers[layers.len() - 1].is_empty() {
             return;
         }
 
@@ -410,6 +581,9 @@ impl SparseVoxelOctreeBuilder {
                     break;
                 }
             }
+        } else if layer.is_empty() {
+            // Root of an octree without any voxels
+            layer.push(SparseVoxelOctreeNode::node(UVec3::ZERO, next_node_size));
         }
 
         (next_node_size, layer)
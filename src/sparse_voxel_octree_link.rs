@@ -21,7 +21,7 @@ use crate::{
 ///
 /// assert_eq!(node, SparseVoxelOctreeLink::new(0, 0, Some(18)));
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct SparseVoxelOctreeLink {
     /// The index of the layer the node is in.
     pub(crate) layer_index: usize,
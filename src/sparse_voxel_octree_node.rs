@@ -1,6 +1,6 @@
 use bevy_math::UVec3;
 
-use crate::{morton_code::MortonCode, sparse_voxel_octree_link::SparseVoxelOctreeLink};
+use crate::{morton_code::MortonCode, sparse_voxel_octree_link::SparseVoxelOctreeLink, AreaType};
 
 #[derive(Debug)]
 pub struct SparseVoxelOctreeNode {
@@ -9,6 +9,11 @@ pub struct SparseVoxelOctreeNode {
     pub(crate) parent: Option<SparseVoxelOctreeLink>,
     pub(crate) first_child: Option<SparseVoxelOctreeLink>,
     pub(crate) is_leaf: bool,
+    /// True if the whole node was never observed. Applicable only for nodes without children.
+    pub(crate) is_unknown: bool,
+    /// Area type of the whole node. Leaf nodes with voxels of different areas store them
+    /// separately.
+    pub(crate) area: AreaType,
     pub(crate) neighbors: [Option<SparseVoxelOctreeLink>; 6],
 }
 
@@ -42,6 +47,8 @@ impl SparseVoxelOctreeNode {
             parent: None,
             first_child: None,
             is_leaf: false,
+            is_unknown: false,
+            area: AreaType::DEFAULT,
             size,
             neighbors: [None; 6],
         }
@@ -53,6 +60,8 @@ impl SparseVoxelOctreeNode {
             parent: None,
             first_child: None,
             is_leaf: true,
+            is_unknown: false,
+            area: AreaType::DEFAULT,
             size: 4,
             neighbors: [None; 6],
         }
@@ -0,0 +1,66 @@
+//! Octrees shared by the tests of several modules.
+
+use bevy_math::{IVec3, UVec3, Vec3};
+
+use crate::{BlockerId, SparseVoxelOctree, SparseVoxelOctreeBuilder, VolumeShape, VoxelizedMesh};
+
+/// Sphere with a radius given in voxels at the center of a 16x16x16 octree.
+pub(crate) fn sphere(radius: f32) -> SparseVoxelOctree {
+    let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    builder.add_mesh(VoxelizedMesh::sphere(radius, 1.0, IVec3::ZERO));
+    builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+    builder.build()
+}
+
+/// Wall at `x = 0` splitting a 16x16x16 octree into two halves, with a 2x2 hole closed by a
+/// disabled door blocker.
+pub(crate) fn wall_with_door() -> (SparseVoxelOctree, BlockerId) {
+    let mut voxels = Vec::new();
+
+    for y in 0..16 {
+        for z in 0..16 {
+            if !(8..10).contains(&y) || !(8..10).contains(&z) {
+                voxels.push(UVec3::new(0, y, z));
+            }
+        }
+    }
+
+    let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::new(0, -8, -8)));
+    builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+    let door = builder.add_blocker(
+        VolumeShape::Box {
+            min: Vec3::new(0.0, 0.0, 0.0),
+            max: Vec3::new(1.0, 2.0, 2.0),
+        },
+        false,
+    );
+
+    (builder.build(), door)
+}
+
+/// Solid 8x8x8 cube with two tunnels along the x and y axes crossing at `(4.5, 4.5, 4.5)`.
+pub(crate) fn crossing_tunnels() -> SparseVoxelOctree {
+    let mut voxels = Vec::new();
+
+    for x in 0..8 {
+        for y in 0..8 {
+            for z in 0..8 {
+                let tunnel_x = y == 4 && z == 4;
+                let tunnel_y = x == 4 && z == 4;
+
+                if !tunnel_x && !tunnel_y {
+                    voxels.push(UVec3::new(x, y, z));
+                }
+            }
+        }
+    }
+
+    let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    builder.add_mesh(VoxelizedMesh::new(voxels, 1.0, IVec3::ZERO));
+    builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+    builder.build()
+}
@@ -0,0 +1,377 @@
+use bevy_math::Vec3;
+
+use crate::SparseVoxelOctree;
+
+/// Limits of the motion along a [`Trajectory`].
+#[derive(Debug, Clone)]
+pub struct TrajectoryLimits {
+    /// Maximal speed in world units per second.
+    pub max_velocity: f32,
+    /// Maximal acceleration and deceleration in world units per second squared, also used as
+    /// the maximal acceleration towards the inside of corners.
+    pub max_acceleration: f32,
+    /// Maximal change of acceleration in world units per second cubed.
+    pub max_jerk: f32,
+    /// Distance from a corner the motion may deviate by, larger values allow faster turns.
+    pub corner_deviation: f32,
+    /// Maximal speed per world unit of distance to the nearest occupied voxel, so the agent
+    /// slows down in narrow spaces. `f32::INFINITY` disables the limit.
+    pub speed_per_clearance: f32,
+    /// Distance within which occupied voxels limit the speed.
+    pub max_clearance: f32,
+}
+
+impl Default for TrajectoryLimits {
+    fn default() -> Self {
+        Self {
+            max_velocity: 5.0,
+            max_acceleration: 5.0,
+            max_jerk: 20.0,
+            corner_deviation: 0.05,
+            speed_per_clearance: 4.0,
+            max_clearance: 2.0,
+        }
+    }
+}
+
+/// Point of a [`Trajectory`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrajectorySample {
+    /// Time since the start of the trajectory in seconds.
+    pub time: f32,
+    /// Distance travelled since the start of the trajectory.
+    pub distance: f32,
+    /// Position in world space.
+    pub position: Vec3,
+    /// Speed in world units per second.
+    pub speed: f32,
+}
+
+/// Time-parameterized motion along waypoints that starts and ends at rest.
+///
+/// Created by [`SparseVoxelOctree::trajectory`]. The waypoints are sampled about once per voxel
+/// and the acceleration between two samples is constant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    samples: Vec<TrajectorySample>,
+}
+
+impl Trajectory {
+    /// Samples of the trajectory ordered by time.
+    #[must_use]
+    pub fn samples(&self) -> &[TrajectorySample] {
+        &self.samples
+    }
+
+    /// Time it takes to reach the last waypoint in seconds.
+    #[must_use]
+    pub fn duration(&self) -> f32 {
+        self.samples.last().map_or(0.0, |sample| sample.time)
+    }
+
+    /// Length of the trajectory in world units.
+    #[must_use]
+    pub fn length(&self) -> f32 {
+        self.samples.last().map_or(0.0, |sample| sample.distance)
+    }
+
+    /// Position at a time. Times outside of the trajectory are clamped.
+    #[must_use]
+    pub fn position(&self, time: f32) -> Vec3 {
+        let (from, to, travelled) = self.segment_at(time);
+        let length = to.distance - from.distance;
+
+        if length <= 0.0 {
+            return from.position;
+        }
+
+        if travelled >= length {
+            return to.position;
+        }
+
+        from.position.lerp(to.position, travelled / length)
+    }
+
+    /// Velocity at a time. Times outside of the trajectory are clamped.
+    #[must_use]
+    pub fn velocity(&self, time: f32) -> Vec3 {
+        let (from, to, _) = self.segment_at(time);
+        let duration = to.time - from.time;
+
+        if duration <= 0.0 {
+            return Vec3::ZERO;
+        }
+
+        let speed =
+            from.speed + (to.speed - from.speed) * ((time - from.time) / duration).clamp(0.0, 1.0);
+
+        (to.position - from.position).normalize_or_zero() * speed
+    }
+
+    /// Samples around a time and the distance travelled from the first one.
+    fn segment_at(&self, time: f32) -> (TrajectorySample, TrajectorySample, f32) {
+        let index = self
+            .samples
+            .partition_point(|sample| sample.time <= time)
+            .clamp(1, self.samples.len().max(2) - 1);
+
+        let from = self.samples[index - 1];
+        let to = self.samples[index.min(self.samples.len() - 1)];
+        let duration = to.time - from.time;
+
+        if duration <= 0.0 {
+            return (from, to, 0.0);
+        }
+
+        let elapsed = (time - from.time).clamp(0.0, duration);
+        let acceleration = (to.speed - from.speed) / duration;
+
+        (
+            from,
+            to,
+            from.speed * elapsed + acceleration * elapsed * elapsed / 2.0,
+        )
+    }
+}
+
+impl SparseVoxelOctree {
+    /// Computes a trajectory along waypoints, such as a smoothed path, that respects the
+    /// limits.
+    ///
+    /// The speed is limited by `max_velocity`, at corners by the deviation allowed by
+    /// `corner_deviation` and near geometry by the [`SparseVoxelOctree::clearance`]. It's then
+    /// reduced so the agent accelerates from the start and decelerates towards the goal within
+    /// `max_acceleration`, ramping the acceleration up by at most `max_jerk`. Jerk is limited
+    /// only approximately where acceleration and deceleration meet.
+    ///
+    /// Returns `None` if there are no waypoints.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{SparseVoxelOctreeBuilder, TrajectoryLimits};
+    /// use bevy_math::Vec3;
+    ///
+    /// let mut builder = SparseVoxelOctreeBuilder::new(1.0);
+    /// builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+    ///
+    /// let octree = builder.build();
+    ///
+    /// let waypoints = [Vec3::new(-6.5, 0.5, 0.5), Vec3::new(6.5, 0.5, 0.5)];
+    /// let trajectory = octree
+    ///     .trajectory(&waypoints, &TrajectoryLimits::default())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(trajectory.position(0.0), waypoints[0]);
+    /// assert_eq!(trajectory.position(trajectory.duration()), waypoints[1]);
+    /// assert!(trajectory.velocity(trajectory.duration() / 2.0).x > 0.0);
+    /// ```
+    #[must_use]
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss,
+        clippy::cast_sign_loss
+    )]
+    pub fn trajectory(&self, waypoints: &[Vec3], limits: &TrajectoryLimits) -> Option<Trajectory> {
+        let waypoints = waypoints
+            .iter()
+            .fold(Vec::new(), |mut waypoints: Vec<Vec3>, waypoint| {
+                if waypoints.last() != Some(waypoint) {
+                    waypoints.push(*waypoint);
+                }
+
+                waypoints
+            });
+
+        let mut samples = vec![TrajectorySample {
+            time: 0.0,
+            distance: 0.0,
+            position: *waypoints.first()?,
+            speed: 0.0,
+        }];
+        let mut speed_limits = vec![0.0];
+
+        for (index, pair) in waypoints.windows(2).enumerate() {
+            let length = pair[0].distance(pair[1]);
+            let pieces = (length / self.voxel_size).ceil().max(1.0) as usize;
+            let distance = samples.last().map_or(0.0, |sample| sample.distance);
+
+            for piece in 1..=pieces {
+                let fraction = piece as f32 / pieces as f32;
+                let position = pair[0].lerp(pair[1], fraction);
+
+                let mut speed_limit = limits.max_velocity.min(
+                    self.clearance(position, limits.max_clearance)
+                        .map_or(f32::INFINITY, |clearance| {
+                            clearance * limits.speed_per_clearance
+                        }),
+                );
+
+                if let (true, Some(next)) = (piece == pieces, waypoints.get(index + 2)) {
+                    speed_limit = speed_limit.min(corner_speed(pair[0], pair[1], *next, limits));
+                }
+
+                samples.push(TrajectorySample {
+                    time: 0.0,
+                    distance: distance + length * fraction,
+                    position,
+                    speed: 0.0,
+                });
+                speed_limits.push(speed_limit);
+            }
+        }
+
+        if let Some(last) = speed_limits.last_mut() {
+            *last = 0.0;
+        }
+
+        let spacings = samples
+            .windows(2)
+            .map(|pair| pair[1].distance - pair[0].distance)
+            .collect::<Vec<_>>();
+
+        // Forward pass accelerates from the start, backward pass decelerates towards the goal.
+        ramp(&mut speed_limits, &spacings, limits);
+
+        speed_limits.reverse();
+        let reversed_spacings = spacings.iter().rev().copied().collect::<Vec<_>>();
+        ramp(&mut speed_limits, &reversed_spacings, limits);
+        speed_limits.reverse();
+
+        for index in 1..samples.len() {
+            let average = (speed_limits[index - 1] + speed_limits[index]) / 2.0;
+
+            samples[index].speed = speed_limits[index];
+            samples[index].time =
+                samples[index - 1].time + spacings[index - 1] / average.max(f32::EPSILON);
+        }
+
+        Some(Trajectory { samples })
+    }
+}
+
+/// Highest speed at a corner that stays within the deviation at the maximal acceleration.
+fn corner_speed(from: Vec3, corner: Vec3, to: Vec3, limits: &TrajectoryLimits) -> f32 {
+    let incoming = (corner - from).normalize_or_zero();
+    let outgoing = (to - corner).normalize_or_zero();
+
+    // Sine of half of the angle between the reversed incoming and the outgoing direction,
+    // 1 when the waypoints are in line and 0 when the path turns back.
+    let sin_half_angle = ((1.0 + incoming.dot(outgoing)) / 2.0).max(0.0).sqrt();
+
+    if sin_half_angle >= 1.0 - f32::EPSILON {
+        return f32::INFINITY;
+    }
+
+    (limits.max_acceleration * limits.corner_deviation * sin_half_angle / (1.0 - sin_half_angle))
+        .sqrt()
+}
+
+/// Lowers the speeds so they never increase faster than the acceleration and jerk allow.
+fn ramp(speeds: &mut [f32], spacings: &[f32], limits: &TrajectoryLimits) {
+    let mut acceleration = 0.0f32;
+
+    for (index, spacing) in spacings.iter().enumerate() {
+        let speed = speeds[index];
+
+        // Time of the step estimated from the current speed or, at rest, from the jerk alone.
+        let duration = if speed > 0.0 {
+            spacing / speed
+        } else {
+            (6.0 * spacing / limits.max_jerk).cbrt()
+        };
+
+        let allowed = (acceleration + limits.max_jerk * duration).min(limits.max_acceleration);
+        let reachable = (speed * speed + 2.0 * allowed * spacing).sqrt();
+
+        if speeds[index + 1] > reachable {
+            speeds[index + 1] = reachable;
+            acceleration = allowed;
+        } else {
+            acceleration = ((speeds[index + 1].powi(2) - speed * speed) / (2.0 * spacing)).max(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_math::{IVec3, UVec3};
+
+    use crate::{SparseVoxelOctreeBuilder, VoxelizedMesh};
+
+    use super::*;
+
+    #[test]
+    fn test_trajectory_slows_at_corners_and_near_walls() {
+        let mut voxels = Vec::new();
+
+        // Wall at x = 0 next to the end of the second leg.
+        for y in 0..4 {
+            for z in 0..4 {
+                voxels.push(UVec3::new(0, y, z));
+            }
+        }
+
+        let mut builder = SparseVoxelOctreeBuilder::new(0.5);
+        builder.add_mesh(VoxelizedMesh::new(voxels, 0.5, IVec3::new(0, 8, -2)));
+        builder.set_bounds(Vec3::new(-8.0, -8.0, -8.0), Vec3::new(8.0, 8.0, 8.0));
+
+        let tree = builder.build();
+        let limits = TrajectoryLimits::default();
+
+        let corner = Vec3::new(-6.0, -6.0, 0.0);
+        let waypoints = [
+            Vec3::new(6.0, -6.0, 0.0),
+            corner,
+            Vec3::new(-6.0, 6.0, 0.0),
+            Vec3::new(-0.5, 6.0, 0.0),
+        ];
+
+        let trajectory = tree.trajectory(&waypoints, &limits).unwrap();
+        let samples = trajectory.samples();
+
+        assert_eq!(trajectory.position(0.0), waypoints[0]);
+        assert_eq!(trajectory.position(trajectory.duration()), waypoints[3]);
+        assert!(samples.first().unwrap().speed.abs() < 1e-6);
+        assert!(samples.last().unwrap().speed.abs() < 1e-6);
+        assert!((trajectory.length() - 29.5).abs() < 1e-3);
+        assert!(trajectory.duration() >= trajectory.length() / limits.max_velocity);
+
+        for pair in samples.windows(2) {
+            let duration = pair[1].time - pair[0].time;
+            let acceleration = (pair[1].speed - pair[0].speed) / duration;
+
+            assert!(duration > 0.0);
+            assert!(pair[1].speed <= limits.max_velocity + 1e-4);
+            assert!(acceleration.abs() <= limits.max_acceleration + 1e-3);
+        }
+
+        let speed_at = |position: Vec3| {
+            samples
+                .iter()
+                .find(|sample| sample.position.distance(position) < 1e-3)
+                .unwrap()
+                .speed
+        };
+
+        // Full speed on straight legs, slower at the corner and next to the wall.
+        assert!((speed_at(Vec3::new(0.0, -6.0, 0.0)) - limits.max_velocity).abs() < 1e-3);
+        assert!(speed_at(corner) < limits.max_velocity / 2.0);
+
+        let near_wall = Vec3::new(-1.5, 6.0, 0.0);
+        let clearance = tree.clearance(near_wall, limits.max_clearance).unwrap();
+
+        assert!(clearance < limits.max_clearance);
+        assert!(speed_at(near_wall) <= clearance * limits.speed_per_clearance + 1e-4);
+
+        let mut time = 0.0;
+
+        while time < trajectory.duration() {
+            let velocity = trajectory.velocity(time);
+
+            assert!(velocity.length() <= limits.max_velocity + 1e-4);
+
+            time += 0.1;
+        }
+    }
+}
@@ -0,0 +1,13 @@
+/// State of a voxel or of a whole node in the sparse voxel octree.
+///
+/// Space that was never voxelized or observed can be marked as unknown, so planners can
+/// decide whether it is safe to traverse it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VoxelState {
+    /// Known to be empty.
+    Free,
+    /// Filled by geometry.
+    Occupied,
+    /// Never observed.
+    Unknown,
+}
@@ -2,11 +2,14 @@ use std::error::Error;
 
 use bevy_math::{IVec3, UVec3};
 
+use crate::AreaType;
+
 /// Collection of voxels that represent a mesh
 pub struct VoxelizedMesh {
     voxels: Vec<UVec3>,
     voxel_size: f32,
     left_top_corner: IVec3,
+    area: AreaType,
 }
 
 impl VoxelizedMesh {
@@ -33,15 +36,40 @@ impl VoxelizedMesh {
             voxels,
             voxel_size,
             left_top_corner,
+            area: AreaType::DEFAULT,
         }
     }
 
+    /// Tags all voxels of the mesh with an area type
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use svo_rs::{AreaType, VoxelizedMesh};
+    /// use bevy_math::IVec3;
+    ///
+    /// let glass = VoxelizedMesh::sphere(2.0, 1.0, IVec3::ZERO).with_area(AreaType::new(3));
+    ///
+    /// assert_eq!(glass.area(), AreaType::new(3));
+    /// ```
+    #[must_use]
+    pub fn with_area(mut self, area: AreaType) -> Self {
+        self.area = area;
+        self
+    }
+
     /// Returns the voxel size of each voxel in the mesh
     #[must_use]
     pub fn voxel_size(&self) -> f32 {
         self.voxel_size
     }
 
+    /// Returns the area type of the voxels in the mesh
+    #[must_use]
+    pub fn area(&self) -> AreaType {
+        self.area
+    }
+
     /// Create a sphere voxelized mesh
     ///
     /// # Example
@@ -167,6 +195,7 @@ impl VoxelizedMesh {
             voxels: voxels.into_iter().collect(),
             voxel_size,
             left_top_corner,
+            area: AreaType::DEFAULT,
         })
     }
 